@@ -0,0 +1,2428 @@
+use crate::baud::{self, BaudMenu};
+use crate::bell::{self, Bell};
+use crate::checksum::Checksum;
+use crate::classify::{Classifier, Severity};
+use crate::clipboard;
+use crate::clock::WallTime;
+use crate::config::{RouteLayout, SPLIT_RANGE};
+use crate::diff::Change;
+use crate::handoff::PortSettings;
+use crate::highlight::{ResponseMatch, SelectionStyle};
+use crate::keymap::Keymap;
+use crate::line::{Direction, Message, Payload, ReaderControl};
+use crate::logfile::Logger;
+use crate::palette::{Action, Palette};
+use crate::pane::{Pane, PaneLine};
+use crate::pipe::Pipe;
+use crate::plot::Plot;
+use crate::prefix::Prefix;
+use crate::route::Route;
+use crate::rules::{CompiledRules, RuleSet};
+use crate::script::{Recording, Script};
+use crate::search::{self, Scope, Search};
+use crate::session::REPLAY_SPEEDS;
+use crate::skip::Skipper;
+use crate::source::Source;
+use crate::state::State;
+use crate::structured::{Level, Structured};
+use crate::success::{self, Success};
+use crate::syslog::NumericLevels;
+use crate::text::{self, ControlDisplay, Decoding, NulDisplay};
+use crate::transfer::{Outcome, Pacing, Transfer};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Color;
+use serialport::SerialPort;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Colour used for separator lines inserted with the mark key.
+pub const MARK_COLOR: Color = Color::Cyan;
+/// Colour used for lines we sent to the device.
+pub const TX_COLOR: Color = Color::LightBlue;
+/// Messages waiting in the channel beyond which the UI counts as lagging.
+pub const LAG_THRESHOLD: usize = 1000;
+/// Percentage of the height `<` and `>` move the split by.
+const SPLIT_STEP: u16 = 5;
+
+/// What a line-input prompt is collecting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+    /// Free text appended to a mark separator.
+    Mark,
+    /// A line to send to the device.
+    Send,
+    /// Path to save the visual selection to.
+    SaveSelection,
+    /// Path to save the lines matching the search to.
+    SaveMatches,
+    /// Slot to keep the macro just recorded in.
+    SaveMacro,
+    /// New baud rate for the port.
+    Baud,
+    /// Line number to jump to in the main pane.
+    GotoLine,
+    /// Name to save the current rule set under.
+    SaveRules,
+    /// Name of a saved rule set to switch to.
+    LoadRules,
+    /// Path of a file to send to the device.
+    SendFile,
+    /// Text to search for in the focused pane.
+    Search,
+}
+
+impl PromptKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptKind::Mark => "Mark: ",
+            PromptKind::Send => "Send: ",
+            PromptKind::SaveSelection => "Save selection to: ",
+            PromptKind::SaveMatches => "Save matching lines to: ",
+            PromptKind::SaveMacro => "Save macro to slot (1-9): ",
+            PromptKind::Baud => "Baud rate: ",
+            PromptKind::GotoLine => ":",
+            PromptKind::SaveRules => "Save rules as: ",
+            PromptKind::LoadRules => "Load rules: ",
+            PromptKind::SendFile => "Send file: ",
+            PromptKind::Search => "Search: ",
+        }
+    }
+}
+
+/// A single-line text prompt shown at the bottom of the screen.
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+/// Identifies one of the panes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaneId {
+    Main,
+    ErrorWarn,
+    Warnings,     // Only shown with the split error layout
+    Route(usize), // Index into `App::routes`
+}
+
+/// A range of lines picked in visual mode, as absolute line numbers so it
+/// survives old lines being trimmed from the pane.
+#[derive(Clone, Copy, Debug)]
+pub struct Selection {
+    pub pane: PaneId,
+    pub anchor: usize,
+    pub cursor: usize,
+}
+
+impl Selection {
+    /// Inclusive `(first, last)` absolute line numbers.
+    pub fn range(&self) -> (usize, usize) {
+        (self.anchor.min(self.cursor), self.anchor.max(self.cursor))
+    }
+}
+
+/// What inspect mode changed, to put back when it ends.
+pub struct InspectMode {
+    line_numbers: bool, // Gutters as they were before
+    byte_counts: bool,
+    froze: Vec<PaneId>, // Panes inspect mode froze, leaving ones the user had
+}
+
+/// A line opened in the byte inspector.
+pub struct Inspection {
+    pub number: usize, // Absolute line number, 1-based
+    pub text: String,
+    pub raw: Option<Vec<u8>>, // Bytes as received, for lines read from the device
+}
+
+/// State of the connection to the port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Link {
+    Connected,
+    /// Lost the port, waiting for the first reconnection attempt.
+    Disconnected,
+    /// Making reconnection attempt `attempt` of `max` (0 = no limit).
+    Reconnecting {
+        attempt: u32,
+        max: u32,
+    },
+    /// Ran out of reconnection attempts.
+    GaveUp,
+}
+
+impl Link {
+    /// Tag shown in the main pane title, empty while connected.
+    pub fn label(self) -> String {
+        match self {
+            Link::Connected => String::new(),
+            Link::Disconnected => " [DISCONNECTED]".to_string(),
+            Link::Reconnecting { attempt, max: 0 } => {
+                format!(" [DISCONNECTED: reconnect attempt {}]", attempt)
+            }
+            Link::Reconnecting { attempt, max } => {
+                format!(" [DISCONNECTED: reconnect attempt {}/{}]", attempt, max)
+            }
+            Link::GaveUp => " [DISCONNECTED: gave up reconnecting]".to_string(),
+        }
+    }
+}
+
+/// Warns when the device has been silent for too long.
+pub struct IdleWatch {
+    pub limit: Duration,
+    pub last_rx: Instant, // When the last line arrived
+    pub warned: bool,     // Already warned about the current silence
+}
+
+impl IdleWatch {
+    pub fn new(limit: Duration) -> Self {
+        IdleWatch {
+            limit,
+            last_rx: Instant::now(),
+            warned: false,
+        }
+    }
+
+    /// Restart the silence timer.
+    pub fn reset(&mut self) {
+        self.last_rx = Instant::now();
+        self.warned = false;
+    }
+
+    /// Tag shown in the main pane title while the device is silent.
+    pub fn label(&self) -> String {
+        if self.warned {
+            format!(" [NO DATA for {}+]", self.limit_label())
+        } else {
+            String::new()
+        }
+    }
+
+    /// `5s`, or `1500ms` for limits that are not whole seconds.
+    pub fn limit_label(&self) -> String {
+        let ms = self.limit.as_millis();
+        if ms.is_multiple_of(1000) {
+            format!("{}s", ms / 1000)
+        } else {
+            format!("{}ms", ms)
+        }
+    }
+}
+
+/// How often the modem control lines are read.
+const SIGNAL_POLL: Duration = Duration::from_millis(200);
+
+/// Footer showing how long ago the last line was received.
+pub struct LineAge {
+    pub last_rx: Option<Instant>, // `None` until the first line
+}
+
+impl LineAge {
+    /// Ages from which the footer turns yellow and then red.
+    const WARN: Duration = Duration::from_secs(5);
+    const STALE: Duration = Duration::from_secs(30);
+
+    pub fn new() -> Self {
+        LineAge { last_rx: None }
+    }
+
+    /// `last line: 2.3s ago`, `last line: 4m 05s ago` or `last line: none`.
+    pub fn label(&self) -> String {
+        let Some(last_rx) = self.last_rx else {
+            return "last line: none".to_string();
+        };
+        let age = last_rx.elapsed();
+        let secs = age.as_secs();
+        if secs < 60 {
+            format!("last line: {:.1}s ago", age.as_secs_f64())
+        } else if secs < 3600 {
+            format!("last line: {}m {:02}s ago", secs / 60, secs % 60)
+        } else {
+            format!("last line: {}h {:02}m ago", secs / 3600, secs / 60 % 60)
+        }
+    }
+
+    /// Green while lines are arriving, yellow and then red as they stop.
+    pub fn color(&self) -> Color {
+        match self.last_rx.map(|last_rx| last_rx.elapsed()) {
+            Some(age) if age < Self::WARN => Color::Green,
+            Some(age) if age < Self::STALE => Color::Yellow,
+            _ => Color::Red,
+        }
+    }
+}
+
+/// Last read state of the input modem control lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signals {
+    pub cts: bool,
+    pub dsr: bool,
+    pub cd: bool,
+    pub ri: bool,
+}
+
+/// Polls the modem control lines for the main pane title.
+pub struct SignalWatch {
+    pub signals: Option<Signals>, // `None` if the port cannot report them
+    pub polled: Option<Instant>,
+}
+
+impl SignalWatch {
+    pub fn new() -> Self {
+        SignalWatch {
+            signals: None,
+            polled: None,
+        }
+    }
+
+    /// Tag such as ` [CTS+ DSR- CD- RI-]`, `+` for asserted.
+    pub fn label(&self) -> String {
+        let Some(signals) = self.signals else {
+            return " [signals n/a]".to_string();
+        };
+        let mark = |on: bool| if on { '+' } else { '-' };
+        format!(
+            " [CTS{} DSR{} CD{} RI{}]",
+            mark(signals.cts),
+            mark(signals.dsr),
+            mark(signals.cd),
+            mark(signals.ri)
+        )
+    }
+}
+
+/// Bytes received and sent.
+pub struct Traffic {
+    pub rx: u64,
+    pub tx: u64,
+    pub cumulative: bool, // Keep counting when the panes are cleared
+}
+
+impl Traffic {
+    pub fn new(cumulative: bool) -> Self {
+        Traffic {
+            rx: 0,
+            tx: 0,
+            cumulative,
+        }
+    }
+
+    /// Tag shown in the main pane title, e.g. ` [RX 142KB / TX 3KB]`.
+    pub fn label(&self) -> String {
+        format!(
+            " [RX {} / TX {}]",
+            human_bytes(self.rx),
+            human_bytes(self.tx)
+        )
+    }
+}
+
+/// `512B`, `142KB` or `3.4MB`.
+fn human_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    if bytes < KB {
+        format!("{}B", bytes)
+    } else if bytes < MB {
+        format!("{}KB", bytes / KB)
+    } else {
+        format!("{:.1}MB", bytes as f64 / MB as f64)
+    }
+}
+
+/// Soft cap on the memory held by the panes.
+pub struct MemoryCap {
+    pub limit: usize, // Bytes
+    pub warned: bool, // Already warned about trimming
+}
+
+impl MemoryCap {
+    pub fn new(megabytes: u64) -> Self {
+        MemoryCap {
+            limit: (megabytes as usize).saturating_mul(1024 * 1024),
+            warned: false,
+        }
+    }
+
+    /// Usage shown in the main pane title, e.g. ` [12.3/64 MB]`.
+    pub fn label(&self, used: usize) -> String {
+        format!(
+            " [{:.1}/{} MB]",
+            used as f64 / (1024.0 * 1024.0),
+            self.limit / (1024 * 1024)
+        )
+    }
+}
+
+/// What happens to received lines that are empty or only whitespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlankLines {
+    Keep,
+    /// Show one of each run of blank lines.
+    Collapse,
+    Drop,
+}
+
+/// Which lines get the time they arrived in a gutter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamps {
+    Off,
+    Every,
+    /// Only lines after a gap longer than the burst gap, so a burst of
+    /// lines shares the timestamp of its first.
+    Bursts,
+}
+
+impl Timestamps {
+    pub fn next(self) -> Self {
+        match self {
+            Timestamps::Off => Timestamps::Every,
+            Timestamps::Every => Timestamps::Bursts,
+            Timestamps::Bursts => Timestamps::Off,
+        }
+    }
+}
+
+/// Options controlling how lines are rendered in the panes.
+#[derive(Clone, Copy, Debug)]
+pub struct Display {
+    pub control: ControlDisplay,   // How control characters are rendered
+    pub nul: NulDisplay,           // How NULs are rendered, before the other control characters
+    pub line_numbers: bool,        // Show absolute line numbers in a gutter
+    pub byte_counts: bool,         // Show each line's length in bytes in a gutter
+    pub compact: bool,             // Drop borders, titles share one status line
+    pub deltas: bool,              // Show the gap since the previous line in a gutter
+    pub delta_threshold: Duration, // Smallest gap worth showing
+    pub timestamps: Timestamps,    // Which lines show when they arrived
+    pub burst_gap: Duration,       // Gap that starts a new burst
+    pub wrap: bool,                // Wrap long lines instead of cutting them off
+    pub wrap_glyph: Option<char>,  // Marks the end of each row a line wraps from
+    pub collapse_runs: usize,      // Shorten runs of this many identical characters, 0 = never
+    pub responses: bool,           // Mark the lines mentioning the last command sent
+    pub heat: bool,                // Colour a gutter column by how busy the link was
+    pub selection: SelectionStyle, // Colors of the lines selected in visual mode
+}
+
+/// All UI state of the monitor.
+pub struct App {
+    pub main: Pane,
+    pub error_warn: Pane,
+    pub main_title: String,
+    pub error_warn_title: String,
+    pub warnings: Pane, // Warnings, when split from the errors
+    pub warnings_title: String,
+    pub split_warnings: bool, // Warnings go to their own pane beside the errors
+    pub top_perc: usize,
+    pub bot_perc: usize,
+    pub split_presets: [u16; 2], // Main pane percentages `=` flips between
+    pub split_preset: usize,     // Preset `=` last switched to
+    pub min_size: (u16, u16),    // Columns and rows below which only a note is drawn
+    pub display: Display,
+    pub prompt: Option<Prompt>,
+    pub focus: PaneId,                       // Pane the navigation keys act on
+    pub scroll_step: usize,                  // Lines moved per arrow key press
+    pub scroll_lock: bool,                   // Navigation keys move every shown pane together
+    pub selection: Option<Selection>,        // Visual mode selection
+    pub inspection: Option<Inspection>,      // Line shown in the byte inspector
+    pub port_commands: Option<Vec<String>>,  // Commands for other terminals, shown in a popup
+    pub inspect_mode: Option<InspectMode>,   // Set while studying a still screen
+    pub last_mark: Option<usize>,            // Absolute line of the newest mark in the main pane
+    pub notice: Option<String>,              // One-off message shown in the bottom row
+    pub writer: Option<Box<dyn SerialPort>>, // Handle used to send to and configure the port
+    pub read_only: bool,                     // Never write to the port
+    pub baud_rate: u32,
+    pub baud_presets: Vec<(char, u32)>, // Alt+key switches to the rate
+    pub keymap: Keymap,                 // Keys for the commands, after the config's changes
+    pub link: Link,
+    pub log: Option<Logger>,
+    pub pipe: Option<Pipe>, // Command received lines are also written to
+    pub classifier: Classifier,
+    pub classify: bool, // Route classified lines to the error pane; off shows one pane
+    pub rules: RuleSet, // Source of the highlights and classifier in use
+    pub transfer: Option<Transfer>, // File being sent
+    pub pacing: Pacing, // How files are sent
+    pub plot: Option<Plot>, // Values extracted for the plot pane
+    pub script: Option<Script>, // Init script or macro still being sent
+    pub prompt_timeout: Option<Duration>, // Macros wait this long for the prompt after each command
+    pub macros: BTreeMap<String, Vec<String>>, // Recorded macros by slot, `1` to `9`
+    pub recording: Option<Recording>, // Macro being recorded
+    recorded: Option<Vec<String>>, // Macro waiting to be given a slot
+    pub idle: Option<IdleWatch>, // Silence warning, if enabled
+    pub skip: Option<Skipper>, // Drops the output right after connecting, if set
+    pub line_age: Option<LineAge>, // Footer with the time since the last line, if shown
+    pub memory: Option<MemoryCap>, // Trim the panes harder past this, if set
+    pub signal_watch: Option<SignalWatch>, // Modem control lines, if shown
+    pub traffic: Option<Traffic>, // Byte counters, if shown
+    pub ui_paused: bool, // Leave incoming messages queued in the channel
+    pub backlog: usize, // Messages queued in the channel after the last frame
+    pub reader: Option<ReaderControl>, // Pauses the reader or changes its decoding
+    pub utf8: Decoding, // UTF-8 decoding `u` cycles back to
+    pub live: Option<usize>, // Absolute line in the main pane still being received
+    pub progress: Option<u16>, // Percentage the live line ends with
+    pub progress_gauge: bool, // Draw `progress` as a gauge
+    pub tab_width: Option<usize>, // Expand tabs in incoming lines to this many columns
+    pub unescape_newlines: bool, // Break received lines at `\n` escapes when showing them
+    pub blank_lines: BlankLines,
+    pub last_blank: bool,               // The last received line was blank
+    pub strip_prefix: Option<Prefix>,   // Removed from received lines before they are shown
+    pub checksum: Option<Checksum>,     // Verifies the checksum received lines carry
+    pub bell: Option<Bell>,             // Rings on the lines it is configured for
+    pub success: Option<Success>,       // Lines meaning the device is ready
+    pub success_flash: Option<Instant>, // When the last success line arrived
+    pub exit_line: Option<String>,      // Success line the program quit on
+    pub sources: Vec<Source>,           // Tags of the ports read, when there are several
+    pub structured: Option<Structured>, // Reads the level of `key=value` lines
+    pub numeric_levels: Option<NumericLevels>, // Reads `<N>` level prefixes
+    pub search: Option<Search>,
+    pub search_hit: Option<(PaneId, usize)>, // Absolute line of the current match
+    pub case_sensitive: bool,                // Used for the next search
+    pub search_scope: Scope,                 // Panes the next search looks in
+    pub error_cursor: Option<usize>,         // Absolute line in the error pane stepped to with j/k
+    pub routes: Vec<Route>,                  // Panes taking the lines that match their pattern
+    pub route_layout: RouteLayout,           // How they sit beside the main pane
+    pub palette: Option<Palette>,            // Command palette, while open
+    pub baud_menu: Option<BaudMenu>,         // Baud rate menu, while open
+    pending_echo: Option<String>,            // Last sent line, until the device echoes it
+    pub last_command: Option<ResponseMatch>, // Words of the last line sent, for `display.responses`
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new() -> Self {
+        App {
+            main: Pane::new(),
+            error_warn: Pane::new(),
+            main_title: "Serial Monitor".to_string(),
+            error_warn_title: "Errors and Warnings".to_string(),
+            warnings: Pane::new(),
+            warnings_title: "Warnings".to_string(),
+            split_warnings: false,
+            top_perc: 80,
+            bot_perc: 20,
+            split_presets: [80, 50],
+            split_preset: 0,
+            min_size: (20, 8),
+            display: Display {
+                control: ControlDisplay::Raw,
+                nul: NulDisplay::Raw,
+                line_numbers: false,
+                byte_counts: false,
+                compact: false,
+                deltas: false,
+                heat: false,
+                delta_threshold: Duration::from_millis(100),
+                timestamps: Timestamps::Off,
+                burst_gap: Duration::from_millis(100),
+                wrap: false,
+                wrap_glyph: Some('↵'),
+                collapse_runs: 256,
+                responses: false,
+                selection: SelectionStyle::new(),
+            },
+            prompt: None,
+            focus: PaneId::Main,
+            scroll_step: 1,
+            scroll_lock: false,
+            selection: None,
+            inspection: None,
+            port_commands: None,
+            inspect_mode: None,
+            last_mark: None,
+            notice: None,
+            writer: None,
+            read_only: false,
+            baud_rate: 115200,
+            baud_presets: Vec::new(),
+            keymap: Keymap::default(),
+            link: Link::Connected,
+            log: None,
+            pipe: None,
+            classifier: Classifier::builtin(false),
+            classify: true,
+            rules: RuleSet::default(),
+            transfer: None,
+            pacing: Pacing::default(),
+            plot: None,
+            script: None,
+            prompt_timeout: None,
+            macros: BTreeMap::new(),
+            recording: None,
+            recorded: None,
+            idle: None,
+            line_age: None,
+            skip: None,
+            memory: None,
+            signal_watch: None,
+            traffic: None,
+            ui_paused: false,
+            backlog: 0,
+            reader: None,
+            utf8: Decoding::Lossy,
+            live: None,
+            progress: None,
+            progress_gauge: false,
+            tab_width: Some(8),
+            unescape_newlines: false,
+            blank_lines: BlankLines::Keep,
+            last_blank: false,
+            strip_prefix: None,
+            checksum: None,
+            bell: None,
+            success: None,
+            success_flash: None,
+            exit_line: None,
+            sources: Vec::new(),
+            structured: None,
+            numeric_levels: None,
+            search: None,
+            search_hit: None,
+            case_sensitive: false,
+            search_scope: Scope::Both,
+            error_cursor: None,
+            routes: Vec::new(),
+            route_layout: RouteLayout::Rows,
+            palette: None,
+            baud_menu: None,
+            pending_echo: None,
+            last_command: None,
+            quit: false,
+        }
+    }
+
+    pub fn pane(&self, id: PaneId) -> &Pane {
+        match id {
+            PaneId::Main => &self.main,
+            PaneId::ErrorWarn => &self.error_warn,
+            PaneId::Warnings => &self.warnings,
+            PaneId::Route(i) => &self.routes[i].pane,
+        }
+    }
+
+    pub fn pane_mut(&mut self, id: PaneId) -> &mut Pane {
+        match id {
+            PaneId::Main => &mut self.main,
+            PaneId::ErrorWarn => &mut self.error_warn,
+            PaneId::Warnings => &mut self.warnings,
+            PaneId::Route(i) => &mut self.routes[i].pane,
+        }
+    }
+
+    /// Pane after `id` in Tab order: main, the routed panes, then the error
+    /// and warning panes that are shown.
+    fn next_pane(&self, id: PaneId) -> PaneId {
+        let next = match id {
+            PaneId::Main => 0,
+            PaneId::Route(i) => i + 1,
+            PaneId::ErrorWarn if self.split_warnings => return PaneId::Warnings,
+            PaneId::ErrorWarn | PaneId::Warnings => return PaneId::Main,
+        };
+        if next < self.routes.len() {
+            PaneId::Route(next)
+        } else if self.classify {
+            PaneId::ErrorWarn
+        } else {
+            PaneId::Main
+        }
+    }
+
+    /// React to a line or connection event from the reader.
+    pub fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Line(payload) => {
+                if !self.skip_line(&payload) {
+                    self.ingest(payload);
+                }
+            }
+            // Partial lines in the skip window go with the rest
+            Message::Update(_) if self.skip.as_ref().is_some_and(Skipper::skipping) => {}
+            // There is one line in progress to show, that of the port sent to
+            Message::Update(payload) if payload.source.is_some_and(|source| source > 0) => {}
+            Message::Update(payload) => self.update_live(payload.text),
+            Message::Disconnected(reason) => {
+                self.end_live();
+                self.writer = None;
+                self.link = Link::Disconnected;
+                self.error_pane()
+                    .push_class(format!("Disconnected: {}", reason), Severity::Error.class());
+            }
+            Message::Reconnecting { attempt, max } => {
+                self.link = Link::Reconnecting { attempt, max }
+            }
+            Message::Reconnected(mut writer) => {
+                // Keep a baud rate changed at runtime across the reconnect
+                if writer.baud_rate().ok() != Some(self.baud_rate) {
+                    let _ = writer.set_baud_rate(self.baud_rate);
+                }
+                self.writer = Some(writer);
+                self.link = Link::Connected;
+                if let Some(idle) = self.idle.as_mut() {
+                    idle.reset();
+                }
+                if let Some(skip) = self.skip.as_mut() {
+                    skip.restart();
+                }
+                self.notice = Some("Reconnected".to_string());
+            }
+            Message::Error(error) => self.error_pane().push_class(error, Severity::Error.class()),
+            Message::Prompt => {
+                if let Some(transfer) = &self.transfer {
+                    transfer.saw_prompt();
+                }
+                if let Some(script) = self.script.as_mut() {
+                    script.saw_prompt();
+                }
+            }
+            Message::Ended => self.notice = Some("Replay finished".to_string()),
+            Message::GaveUp => {
+                self.link = Link::GaveUp;
+                self.error_pane().push_class(
+                    "Giving up: could not reconnect to the port".to_string(),
+                    Severity::Error.class(),
+                );
+            }
+        }
+    }
+
+    /// Whether `payload` falls in the `--skip-initial` window and is dropped.
+    /// Notes how much was dropped once a line gets through.
+    fn skip_line(&mut self, payload: &Payload) -> bool {
+        let Some(skip) = self.skip.as_mut() else {
+            return false;
+        };
+        if payload.direction == Direction::Rx && skip.skip_line() {
+            return true;
+        }
+        if let Some(note) = skip.note() {
+            self.notice = Some(note);
+        }
+        false
+    }
+
+    /// Warn once per silence when no line has arrived within the idle limit.
+    pub fn check_idle(&mut self) {
+        let Some(idle) = self.idle.as_mut() else {
+            return;
+        };
+        // A lost port is already reported; don't pile silence warnings on top
+        if idle.warned || self.link != Link::Connected || idle.last_rx.elapsed() < idle.limit {
+            return;
+        }
+        idle.warned = true;
+        let line = format!("No data for {}", idle.limit_label());
+        self.record(&line);
+        self.warning_pane()
+            .push_class(line, Severity::Warning.class());
+    }
+
+    /// Show a diff between two captures: the whole diff in the main pane and
+    /// only the changed lines, numbered, in the error/warning pane.
+    pub fn show_diff(&mut self, changes: &[Change], old_name: &str, new_name: &str) {
+        self.main_title = format!("Diff {} → {}", old_name, new_name);
+        self.error_warn_title = "Changes".to_string();
+        self.split_warnings = false;
+        self.main.capacity = usize::MAX;
+        self.error_warn.capacity = usize::MAX;
+        let (mut old_number, mut new_number) = (0, 0);
+        for change in changes {
+            match *change {
+                Change::Same(line) => {
+                    old_number += 1;
+                    new_number += 1;
+                    self.main.push(format!("  {}", line), Color::Gray);
+                }
+                Change::Removed(line) => {
+                    old_number += 1;
+                    self.main.push(format!("- {}", line), Color::Red);
+                    self.error_warn
+                        .push(format!("-{:>6}: {}", old_number, line), Color::Red);
+                }
+                Change::Added(line) => {
+                    new_number += 1;
+                    self.main.push(format!("+ {}", line), Color::Green);
+                    self.error_warn
+                        .push(format!("+{:>6}: {}", new_number, line), Color::Green);
+                }
+            }
+        }
+        self.check_memory();
+        // Start at the top rather than following the end
+        self.main.home();
+        self.error_warn.home();
+    }
+
+    /// Show the line still being received as it is redrawn, in place at
+    /// the end of the main pane.
+    fn update_live(&mut self, mut text: String) {
+        if let Some(prefix) = &self.strip_prefix {
+            text = prefix.strip(&text).to_string();
+        }
+        if let Some(width) = self.tab_width {
+            text = text::expand_tabs(&text, width).into_owned();
+        }
+        if let Some(idle) = self.idle.as_mut() {
+            idle.reset();
+        }
+        if let Some(age) = self.line_age.as_mut() {
+            age.last_rx = Some(Instant::now());
+        }
+        self.progress = text::trailing_percent(&text);
+        // Lines sent meanwhile may follow it, so it is found by position
+        match self.live.filter(|&live| self.main.get(live).is_some()) {
+            Some(live) => self.main.replace(live, text),
+            None => {
+                self.main.push(text, Color::Green);
+                self.live = Some(self.main.trimmed + self.main.lines.len() - 1);
+            }
+        }
+    }
+
+    /// Text of the line still being received, if there is one.
+    pub fn live_text(&self) -> Option<&str> {
+        let line = self.main.get(self.live?)?;
+        Some(&line.text)
+    }
+
+    /// Whether the line still being received is shown as it arrives.
+    pub fn shows_partial(&self) -> bool {
+        self.reader
+            .as_ref()
+            .is_some_and(|reader| reader.shows_partial())
+    }
+
+    fn toggle_partial(&mut self) {
+        let Some(reader) = &self.reader else {
+            self.notice = Some("No port to read from".to_string());
+            return;
+        };
+        let show = !reader.shows_partial();
+        reader.set_show_partial(show);
+        // A line redrawn with `\r` comes back with its next update
+        if !show {
+            self.end_live();
+        }
+    }
+
+    /// Drop the live line, wherever it now is, before the finished line
+    /// takes its place.
+    fn end_live(&mut self) {
+        self.progress = None;
+        if let Some(live) = self.live.take() {
+            self.main.remove(live);
+        }
+    }
+
+    /// Route a line to the right pane.
+    pub fn ingest(&mut self, mut payload: Payload) {
+        // The finished line replaces its live version
+        if payload.direction == Direction::Rx {
+            self.end_live();
+        }
+        // A device echoing our command back is still our command
+        if payload.direction == Direction::Rx
+            && self.pending_echo.as_deref() == Some(payload.text.trim())
+        {
+            self.pending_echo = None;
+            payload.direction = Direction::Tx;
+        }
+        if payload.direction == Direction::Rx {
+            if let Some(traffic) = self.traffic.as_mut() {
+                // The line as received is not kept, so count its text and newline
+                traffic.rx += payload.text.len() as u64 + 1;
+            }
+            if let Some(idle) = self.idle.as_mut() {
+                idle.reset();
+            }
+            if let Some(age) = self.line_age.as_mut() {
+                age.last_rx = Some(Instant::now());
+            }
+            if let Some(plot) = self.plot.as_mut() {
+                plot.observe(&payload.text);
+            }
+            if let Some(bell) = self.bell.as_mut() {
+                // A missed bell is not worth interrupting the display for
+                let _ = bell.check(&payload.text);
+            }
+            self.check_success(&payload.text);
+            let warning = self
+                .pipe
+                .as_mut()
+                .and_then(|pipe| pipe.send(payload.text.clone()));
+            if let Some(warning) = warning {
+                self.warning_pane()
+                    .push_class(warning, Severity::Warning.class());
+            }
+        }
+
+        let mismatch = match &self.checksum {
+            Some(checksum) if payload.direction == Direction::Rx => checksum.check(&payload.text),
+            _ => None,
+        };
+
+        self.record_from(&payload.text, payload.source);
+        // Blank lines are logged like any other, only the display skips them
+        if payload.direction == Direction::Rx {
+            let blank = payload.text.trim().is_empty();
+            let repeated = blank && self.last_blank;
+            self.last_blank = blank;
+            match self.blank_lines {
+                BlankLines::Drop if blank => return,
+                BlankLines::Collapse if repeated => return,
+                _ => {}
+            }
+        }
+        if let Some(width) = self.tab_width {
+            payload.text = text::expand_tabs(&payload.text, width).into_owned();
+        }
+        let level = self.level_of(&payload);
+        // A line failing its checksum is an error whatever it says
+        let class = match mismatch {
+            Some(_) => Some(Severity::Error.class()),
+            None if self.classify => match level {
+                Some(level) => level.class(),
+                None => self.classifier.classify(&payload),
+            },
+            None => None,
+        };
+        let route = match payload.direction {
+            Direction::Rx => self
+                .routes
+                .iter()
+                .position(|route| route.matches(&payload.text)),
+            Direction::Tx => None,
+        };
+        // Only the display loses the prefix; the log and classifier see it all
+        if let (Some(levels), Direction::Rx) = (&self.numeric_levels, payload.direction) {
+            payload.text = levels.strip(&payload.text).to_string();
+        }
+        if let (Some(prefix), Direction::Rx) = (&self.strip_prefix, payload.direction) {
+            payload.text = prefix.strip(&payload.text).to_string();
+        }
+        if let Some(shown) = self
+            .structured
+            .as_mut()
+            .filter(|_| payload.direction == Direction::Rx)
+            .and_then(|structured| structured.columns(&payload.text))
+        {
+            payload.text = shown;
+        }
+        if let Some(mismatch) = mismatch {
+            payload.text = format!("{}  [{}]", payload.text, mismatch);
+        }
+        // Every piece of an unescaped line goes where the whole line would
+        let pieces = if self.unescape_newlines && payload.direction == Direction::Rx {
+            text::split_escaped_newlines(&payload.text)
+        } else {
+            vec![payload.text]
+        };
+        // Each piece keeps the bytes of the whole line it came from
+        for piece in pieces {
+            let mut line = PaneLine::new(piece, Color::Green);
+            line.raw = payload.raw.clone();
+            line.source = payload.source;
+            let pane = match class {
+                Some(class) => {
+                    line.color = class.color;
+                    line.severity = Some(class.severity);
+                    if class.severity == Severity::Warning {
+                        self.warning_pane()
+                    } else {
+                        self.error_pane()
+                    }
+                }
+                None if payload.direction == Direction::Tx => {
+                    line.color = TX_COLOR;
+                    &mut self.main
+                }
+                None => {
+                    line.color = level.map_or(Color::Green, Level::color);
+                    match route {
+                        Some(i) => &mut self.routes[i].pane,
+                        None => &mut self.main,
+                    }
+                }
+            };
+            pane.push_line(line);
+        }
+        // Responses are the lines after the command and its echo
+        if payload.direction == Direction::Tx {
+            if let Some(last_command) = self.last_command.as_mut() {
+                last_command.restart();
+            }
+        }
+        self.check_memory();
+    }
+
+    /// Level of a received line from its numeric prefix or, for a
+    /// structured line, its level key, if it gives one we recognize.
+    fn level_of(&self, payload: &Payload) -> Option<Level> {
+        if payload.direction != Direction::Rx {
+            return None;
+        }
+        let numeric = self
+            .numeric_levels
+            .as_ref()
+            .and_then(|levels| levels.level(&payload.text));
+        numeric.or_else(|| {
+            self.structured
+                .as_ref()
+                .and_then(|structured| structured.level(&payload.text))
+        })
+    }
+
+    /// Pane errors and warnings go to: the error pane, or the only one
+    /// shown when classification is off.
+    fn error_pane(&mut self) -> &mut Pane {
+        if self.classify {
+            &mut self.error_warn
+        } else {
+            &mut self.main
+        }
+    }
+
+    /// Pane warnings go to: the warning pane when split from the errors,
+    /// otherwise the same one as errors.
+    fn warning_pane(&mut self) -> &mut Pane {
+        if self.split_warnings {
+            &mut self.warnings
+        } else {
+            self.error_pane()
+        }
+    }
+
+    /// Approximate bytes held by all panes.
+    pub fn memory_used(&self) -> usize {
+        let routed: usize = self.routes.iter().map(|route| route.pane.bytes).sum();
+        self.main.bytes + self.error_warn.bytes + self.warnings.bytes + routed
+    }
+
+    /// Once the panes near the memory cap, drop their oldest lines until a
+    /// quarter of it is free again, warning the first time.
+    fn check_memory(&mut self) {
+        let used = self.memory_used();
+        let Some(memory) = self.memory.as_mut() else {
+            return;
+        };
+        let limit = memory.limit;
+        if used < limit / 10 * 9 {
+            return;
+        }
+        let target = limit / 4 * 3;
+        let warn = !memory.warned;
+        memory.warned = true;
+        while self.memory_used() > target {
+            // Take from whichever pane holds most, a chunk at a time
+            let pane = [&mut self.main, &mut self.error_warn, &mut self.warnings]
+                .into_iter()
+                .chain(self.routes.iter_mut().map(|route| &mut route.pane))
+                .max_by_key(|pane| pane.bytes)
+                .expect("there is always a main pane");
+            if pane.lines.is_empty() {
+                break;
+            }
+            pane.drop_oldest((pane.lines.len() / 10).max(1));
+        }
+        if warn {
+            let line = format!(
+                "Memory use reached the {} MB cap; dropping the oldest lines",
+                limit / (1024 * 1024)
+            );
+            self.warning_pane()
+                .push_class(line, Severity::Warning.class());
+        }
+    }
+
+    /// Write a line to the device and show it in the main pane. Returns
+    /// whether it was written.
+    pub fn send(&mut self, line: String) -> bool {
+        if self.read_only {
+            self.refuse_read_only();
+            return false;
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return false;
+        };
+        let result = writer
+            .write_all(line.as_bytes())
+            .and_then(|_| writer.write_all(b"\r\n"))
+            .and_then(|_| writer.flush());
+        match result {
+            Ok(()) => {
+                if let Some(traffic) = self.traffic.as_mut() {
+                    traffic.tx += line.len() as u64 + 2;
+                }
+                let sent = line.trim();
+                self.pending_echo = (!sent.is_empty()).then(|| sent.to_string());
+                self.last_command = Some(ResponseMatch::new(sent));
+                self.ingest(Payload::tx(line));
+                true
+            }
+            Err(e) => {
+                self.error_pane()
+                    .push_class(format!("Send failed: {}", e), Severity::Error.class());
+                false
+            }
+        }
+    }
+
+    fn refuse_read_only(&mut self) {
+        self.notice = Some("Read-only: nothing is written to the port".to_string());
+    }
+
+    /// Start sending the file at `path` in the background.
+    fn send_file(&mut self, path: &str) {
+        if self.transfer.is_some() {
+            self.notice = Some("Already sending a file".to_string());
+            return;
+        }
+        let Some(writer) = self.writer.as_ref() else {
+            self.notice = Some("Not connected".to_string());
+            return;
+        };
+        let started = writer
+            .try_clone()
+            .map_err(io::Error::from)
+            .and_then(|writer| {
+                Transfer::start(Path::new(path.trim()), writer, self.pacing.clone())
+            });
+        match started {
+            Ok(transfer) => self.transfer = Some(transfer),
+            Err(e) => self.notice = Some(format!("Send file failed: {}", e)),
+        }
+    }
+
+    /// Stop the file being sent; `poll_transfer` reports once it has.
+    fn cancel_transfer(&mut self) {
+        match &self.transfer {
+            Some(transfer) => transfer.cancel(),
+            None => self.notice = Some("Not sending a file".to_string()),
+        }
+    }
+
+    /// Pick up progress from the file being sent.
+    pub fn poll_transfer(&mut self) {
+        let Some(transfer) = self.transfer.as_mut() else {
+            return;
+        };
+        let before = transfer.sent;
+        let result = transfer.poll();
+        if let Some(traffic) = self.traffic.as_mut() {
+            traffic.tx += transfer.sent - before;
+        }
+        let Some(result) = result else {
+            return;
+        };
+        let Some(transfer) = self.transfer.take() else {
+            return;
+        };
+        match result {
+            Outcome::Sent => {
+                self.notice = Some(format!("Sent {} ({} bytes)", transfer.name, transfer.total))
+            }
+            Outcome::Cancelled(sent) => {
+                self.notice = Some(format!(
+                    "Cancelled sending {} after {} of {} bytes",
+                    transfer.name, sent, transfer.total
+                ))
+            }
+            Outcome::Failed(e) => self.error_pane().push_class(e, Severity::Error.class()),
+        }
+    }
+
+    /// Read the modem control lines again once `SIGNAL_POLL` has passed.
+    pub fn poll_signals(&mut self) {
+        let Some(watch) = self.signal_watch.as_mut() else {
+            return;
+        };
+        if watch
+            .polled
+            .is_some_and(|polled| polled.elapsed() < SIGNAL_POLL)
+        {
+            return;
+        }
+        watch.polled = Some(Instant::now());
+        watch.signals = self.writer.as_mut().and_then(|port| {
+            Some(Signals {
+                cts: port.read_clear_to_send().ok()?,
+                dsr: port.read_data_set_ready().ok()?,
+                cd: port.read_carrier_detect().ok()?,
+                ri: port.read_ring_indicator().ok()?,
+            })
+        });
+    }
+
+    /// Send a line typed or pasted at the send prompt, recording it if a
+    /// macro is being recorded and it was written.
+    fn send_typed(&mut self, line: String) {
+        if !self.send(line.clone()) {
+            return;
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.sent(&line);
+        }
+    }
+
+    /// Start recording a macro, or stop and ask for the slot to keep it in.
+    fn toggle_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            self.recording = Some(Recording::new(self.prompt_timeout.is_none()));
+            self.notice = Some("Recording macro: send lines with t, then Q to stop".to_string());
+            return;
+        };
+        if recording.sends() == 0 {
+            self.notice = Some("Nothing recorded".to_string());
+            return;
+        }
+        self.recorded = Some(recording.finish());
+        self.open_prompt(PromptKind::SaveMacro);
+    }
+
+    /// Keep the macro just recorded in the slot typed at the prompt, for
+    /// this and later sessions.
+    fn save_macro(&mut self, input: &str) {
+        let slot = input.trim();
+        if !matches!(slot, "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9") {
+            self.notice = Some(format!("Not a macro slot: {:?}, pick 1 to 9", slot));
+            self.open_prompt(PromptKind::SaveMacro);
+            return;
+        }
+        let Some(lines) = self.recorded.take() else {
+            return;
+        };
+        self.macros.insert(slot.to_string(), lines);
+        let mut state = State::load();
+        state.macros = self.macros.clone();
+        self.notice = Some(match state.save() {
+            Ok(()) => format!("Saved macro; F{} runs it", slot),
+            Err(e) => format!("Macro kept for this session only: {}", e),
+        });
+    }
+
+    /// Drop the macro just recorded when asking for its slot is cancelled.
+    fn discard_macro(&mut self) {
+        if self.recorded.take().is_some() {
+            self.notice = Some("Macro discarded".to_string());
+        }
+    }
+
+    fn run_macro(&mut self, slot: u8) {
+        if let Some(script) = &self.script {
+            self.notice = Some(format!("Still running {}", script.name));
+            return;
+        }
+        let Some(lines) = self.macros.get(&slot.to_string()) else {
+            self.notice = Some(format!("No macro in F{}; Q records one", slot));
+            return;
+        };
+        let mut script = Script::from_macro(format!("macro F{}", slot), lines);
+        if let Some(timeout) = self.prompt_timeout {
+            script.wait_for_prompts(timeout);
+        }
+        self.script = Some(script);
+        self.poll_script();
+    }
+
+    /// Send whatever init script or macro commands are due.
+    pub fn poll_script(&mut self) {
+        // Hold the rest of the script until the port is back
+        if self.link != Link::Connected {
+            return;
+        }
+        let Some(script) = self.script.as_mut() else {
+            return;
+        };
+        if script.prompt_overdue() {
+            let warning = format!(
+                "Stopped {}: no prompt within {:?} of the last command",
+                script.name, self.pacing.prompt_timeout
+            );
+            self.warning_pane()
+                .push_class(warning, Severity::Warning.class());
+            self.script = None;
+            return;
+        }
+        let mut commands = Vec::new();
+        while let Some(command) = script.next_command() {
+            commands.push(command);
+        }
+        if script.is_done() {
+            self.notice = Some(format!("Ran {}", script.name));
+            self.script = None;
+        }
+        for command in commands {
+            self.send(command);
+        }
+    }
+
+    /// Switch the port to a new baud rate typed at the prompt.
+    fn set_baud_rate(&mut self, input: &str) {
+        let Ok(rate) = input.trim().parse::<u32>() else {
+            self.notice = Some(format!("Not a baud rate: {}", input.trim()));
+            return;
+        };
+        self.switch_baud_rate(rate);
+    }
+
+    /// Switch the port to `rate`, reporting a rejected rate in the error pane.
+    fn switch_baud_rate(&mut self, rate: u32) {
+        if self.read_only {
+            self.refuse_read_only();
+            return;
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        match writer.set_baud_rate(rate) {
+            Ok(()) => {
+                self.baud_rate = rate;
+                self.notice = Some(format!("Baud rate set to {}", rate));
+            }
+            Err(e) => self
+                .error_pane()
+                .push_class(baud::describe_error(rate, &e), Severity::Error.class()),
+        }
+    }
+
+    /// Tag shown in the main pane title naming the baud preset in use, if
+    /// any presets are configured.
+    pub fn baud_label(&self) -> String {
+        if self.baud_presets.is_empty() {
+            return String::new();
+        }
+        match self
+            .baud_presets
+            .iter()
+            .find(|&&(_, rate)| rate == self.baud_rate)
+        {
+            Some((key, rate)) => format!(" [{} baud, preset {}]", rate, key),
+            None => format!(" [{} baud]", self.baud_rate),
+        }
+    }
+
+    /// Scroll the focused pane so absolute line `input` (1-based) is at the top.
+    fn goto_line(&mut self, input: &str) {
+        let Ok(number) = input.trim().parse::<usize>() else {
+            self.notice = Some(format!("Not a line number: {}", input.trim()));
+            return;
+        };
+        let pane = self.pane_mut(self.focus);
+        let first = pane.trimmed + 1;
+        let last = pane.trimmed + pane.lines.len();
+        if number < first {
+            self.notice = Some(format!(
+                "Line {} has scrolled out of the buffer (oldest is {})",
+                number, first
+            ));
+        } else if number > last {
+            self.notice = Some(format!("Line {} has not been received yet", number));
+        } else {
+            pane.scroll_offset = number - first;
+            pane.is_scrolled = true;
+        }
+    }
+
+    /// Switch to `rules`, already compiled.
+    pub fn use_rules(&mut self, rules: RuleSet, compiled: CompiledRules) {
+        for route in &mut self.routes {
+            route.pane.highlights = compiled.main.clone();
+        }
+        self.main.highlights = compiled.main;
+        self.warnings.highlights = compiled.errors.clone();
+        self.error_warn.highlights = compiled.errors;
+        self.classifier = compiled.classifier;
+        self.rules = rules;
+    }
+
+    fn save_rules(&mut self, name: &str) {
+        self.notice = Some(match self.rules.save(name) {
+            Ok(path) => format!("Saved rules to {}", path.display()),
+            Err(e) => e,
+        });
+    }
+
+    /// Switch to a saved rule set. Highlights apply to lines already shown;
+    /// classification only to lines received from now on.
+    fn load_rules(&mut self, name: &str) {
+        let loaded = RuleSet::load(name).and_then(|rules| Ok((rules.compile()?, rules)));
+        self.notice = Some(match loaded {
+            Ok((compiled, rules)) => {
+                self.use_rules(rules, compiled);
+                format!("Loaded rules {}", name.trim())
+            }
+            Err(e) => e,
+        });
+    }
+
+    /// Insert a `──── MARK 14:02:11 ────` separator into the main pane.
+    pub fn mark(&mut self, label: &str) {
+        let label = label.trim();
+        let line = if label.is_empty() {
+            format!("──── MARK {} ────", WallTime::now().hms())
+        } else {
+            format!("──── MARK {} {} ────", WallTime::now().hms(), label)
+        };
+        self.record(&line);
+        self.last_mark = Some(self.main.trimmed + self.main.lines.len());
+        if self.main.hide_before.is_some() {
+            self.main.hide_before = self.last_mark;
+        }
+        self.main.push(line, MARK_COLOR);
+    }
+
+    /// Hide the main pane's lines before the newest mark, or show them all
+    /// again.
+    fn toggle_since_mark(&mut self) {
+        if self.main.hide_before.take().is_some() {
+            return;
+        }
+        match self.last_mark {
+            Some(mark) => self.main.hide_before = Some(mark),
+            None => self.notice = Some("No mark yet (m adds one)".to_string()),
+        }
+    }
+
+    /// Append a displayed line to the log file, if logging.
+    fn record(&mut self, line: &str) {
+        self.record_from(line, None);
+    }
+
+    /// Log a line from port `source`, when several are read.
+    fn record_from(&mut self, line: &str, source: Option<usize>) {
+        let Some(log) = self.log.as_mut() else {
+            return;
+        };
+        if let Err(e) = log.write_from(line, source) {
+            // Stop logging rather than reporting the same failure for every line
+            self.log = None;
+            self.error_pane()
+                .push_class(format!("Logging stopped: {}", e), Severity::Error.class());
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        self.notice = None;
+        if self.prompt.is_some() {
+            self.handle_prompt_key(key);
+            return;
+        }
+        if self.palette.is_some() {
+            self.handle_palette_key(key);
+            return;
+        }
+        if self.baud_menu.is_some() {
+            self.handle_baud_menu_key(key);
+            return;
+        }
+        if self.inspection.is_some() {
+            // Any of the usual close keys dismisses the inspector
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('q' | 'i')) {
+                self.inspection = None;
+            }
+            return;
+        }
+        if self.port_commands.is_some() {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter) {
+                self.port_commands = None;
+            }
+            return;
+        }
+        if self.selection.is_some() {
+            self.handle_visual_key(key);
+            return;
+        }
+
+        // Alt+digit switches to a baud rate preset
+        if let (KeyCode::Char(c), true) = (key.code, key.modifiers.contains(KeyModifiers::ALT)) {
+            if let Some(&(_, rate)) = self.baud_presets.iter().find(|&&(key, _)| key == c) {
+                self.switch_baud_rate(rate);
+                return;
+            }
+        }
+
+        if key.code == KeyCode::Char('p') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.palette = Some(Palette::default());
+            return;
+        }
+
+        match self.keymap.resolve(key.code) {
+            Some(Action::Key(code)) => self.run_key(code),
+            Some(action) => self.run_action(action),
+            None => {}
+        }
+    }
+
+    /// Carry out what `code` does by default.
+    fn run_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Tab => self.focus = self.next_pane(self.focus),
+            KeyCode::Down => self.navigate(self.scroll_step, Pane::scroll_down),
+            KeyCode::Up => self.navigate(self.scroll_step, Pane::scroll_up),
+            KeyCode::PageDown => self.navigate(self.page_height(), Pane::scroll_down),
+            KeyCode::PageUp => self.navigate(self.page_height(), Pane::scroll_up),
+            KeyCode::Home => self.navigate(1, Pane::home),
+            KeyCode::End => self.navigate(1, Pane::follow),
+            KeyCode::Char('a') => self.main.follow(),
+            KeyCode::Char('w') => self.scroll(PaneId::ErrorWarn, true),
+            KeyCode::Char('s') => self.scroll(PaneId::ErrorWarn, false),
+            KeyCode::Char('+') => self.set_scroll_step(self.scroll_step + 1),
+            KeyCode::Char('-') => self.set_scroll_step(self.scroll_step.saturating_sub(1)),
+            KeyCode::Char('d') => self.error_warn.follow(),
+            KeyCode::Char('e') => self.jump_to_last_error(),
+            KeyCode::Char('j') => self.step_error(true),
+            KeyCode::Char('k') => self.step_error(false),
+            KeyCode::Esc if self.transfer.is_some() => self.cancel_transfer(),
+            KeyCode::Esc => self.error_cursor = None,
+            KeyCode::Char('C') => self.clear(self.focus),
+            KeyCode::Char('p') => self.ui_paused = !self.ui_paused,
+            KeyCode::Char('P') => self.toggle_read_pause(),
+            KeyCode::Char(' ') => self.toggle_replay_pause(),
+            KeyCode::Char('}') => self.step_replay_speed(true),
+            KeyCode::Char('{') => self.step_replay_speed(false),
+            KeyCode::Char('u') => self.cycle_decoding(),
+            KeyCode::Char('z') => self.main.toggle_freeze(),
+            KeyCode::Char('Z') => self.toggle_error_freeze(),
+            KeyCode::Char('c') => {
+                // Cycle how control characters are rendered
+                self.display.control = self.display.control.next();
+            }
+            KeyCode::Char('n') => self.display.line_numbers = !self.display.line_numbers,
+            KeyCode::Char('l') => self.display.byte_counts = !self.display.byte_counts,
+            KeyCode::Char('h') => self.display.responses = !self.display.responses,
+            KeyCode::Char('b') => self.display.compact = !self.display.compact,
+            KeyCode::Char('=') => {
+                self.split_preset = 1 - self.split_preset;
+                self.set_split(self.split_presets[self.split_preset]);
+            }
+            KeyCode::Char('>') => self.set_split(self.top_perc as u16 + SPLIT_STEP),
+            KeyCode::Char('<') => self.set_split((self.top_perc as u16).saturating_sub(SPLIT_STEP)),
+            KeyCode::Char('W') => self.display.wrap = !self.display.wrap,
+            KeyCode::Char('D') => self.display.deltas = !self.display.deltas,
+            KeyCode::Char('H') => self.display.heat = !self.display.heat,
+            KeyCode::Char('A') => self.toggle_scroll_lock(),
+            KeyCode::Char('R') => self.toggle_partial(),
+            KeyCode::Char('T') => self.display.timestamps = self.display.timestamps.next(),
+            KeyCode::Char(':') => self.palette = Some(Palette::default()),
+            KeyCode::Char('/') => {
+                self.open_prompt(PromptKind::Search);
+                // Start from the previous query so it is easy to refine
+                if let (Some(prompt), Some(search)) = (self.prompt.as_mut(), &self.search) {
+                    prompt.input = search.query.clone();
+                }
+            }
+            KeyCode::Char(']') => self.find(true),
+            KeyCode::Char('[') => self.find(false),
+            KeyCode::Char('m') => self.mark(""),
+            KeyCode::Char('O') => self.toggle_since_mark(),
+            // Ask for a label before inserting the mark
+            KeyCode::Char('M') => self.open_prompt(PromptKind::Mark),
+            KeyCode::Char('t' | 'F') | KeyCode::F(1..=9) if self.inspect_mode.is_some() => {
+                self.notice = Some("Sending is off in inspect mode (I to leave)".to_string());
+            }
+            KeyCode::Char('t' | 'F' | 'B') | KeyCode::F(1..=9) if self.read_only => {
+                self.refuse_read_only()
+            }
+            KeyCode::Char('t') => {
+                if let Some(recording) = self.recording.as_mut() {
+                    recording.prompt_opened();
+                }
+                self.open_prompt(PromptKind::Send);
+            }
+            KeyCode::Char('F') => self.open_prompt(PromptKind::SendFile),
+            KeyCode::Char('v') => self.start_selection(),
+            KeyCode::Char('I') => self.toggle_inspect_mode(),
+            KeyCode::Char('Y') => self.copy_error_warn(),
+            KeyCode::Char('B') => self.baud_menu = Some(BaudMenu::new(self.baud_rate)),
+            KeyCode::Char('S') => self.open_prompt(PromptKind::SaveRules),
+            KeyCode::Char('Q') => self.toggle_recording(),
+            KeyCode::F(slot @ 1..=9) => self.run_macro(slot),
+            KeyCode::Char('X') if self.search.is_none() => {
+                self.notice = Some("No search; press / to start one".to_string());
+            }
+            KeyCode::Char('X') => self.open_prompt(PromptKind::SaveMatches),
+            KeyCode::Char('L') => self.open_prompt(PromptKind::LoadRules),
+            _ => {}
+        }
+    }
+
+    fn handle_palette_key(&mut self, key: KeyEvent) {
+        let Some(palette) = self.palette.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Up => palette.select_previous(),
+            KeyCode::Down => palette.select_next(),
+            KeyCode::Enter => {
+                let palette = self.palette.take().expect("palette is open");
+                let query = palette.query.trim();
+                // A bare number jumps to that line, like `:N` in vi
+                if !query.is_empty() && query.chars().all(|c| c.is_ascii_digit()) {
+                    self.goto_line(query);
+                } else if let Some(command) = palette.matches().get(palette.selected) {
+                    self.run_action(command.action);
+                }
+            }
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                palette.query.push(c);
+                palette.selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_baud_menu_key(&mut self, key: KeyEvent) {
+        let Some(menu) = self.baud_menu.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.baud_menu = None,
+            KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+            KeyCode::Home => menu.selected = 0,
+            KeyCode::End => menu.selected = menu.rates.len(),
+            KeyCode::Enter => {
+                let menu = self.baud_menu.take().expect("baud menu is open");
+                match menu.choice() {
+                    Some(rate) => self.switch_baud_rate(rate),
+                    None => self.open_prompt(PromptKind::Baud),
+                }
+            }
+            // Typing a number goes straight to the custom entry
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                self.baud_menu = None;
+                self.open_prompt(PromptKind::Baud);
+                if let Some(prompt) = self.prompt.as_mut() {
+                    prompt.input.push(c);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Carry out a command chosen in the palette.
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Key(code) => self.run_key(code),
+            Action::GotoLine => self.open_prompt(PromptKind::GotoLine),
+            Action::ToggleCaseSensitive => {
+                self.case_sensitive = !self.case_sensitive;
+                self.refresh_search();
+            }
+            Action::CycleSearchScope => {
+                self.search_scope = self.search_scope.next();
+                self.refresh_search();
+            }
+            Action::ToggleProgressGauge => self.progress_gauge = !self.progress_gauge,
+            Action::ResetTraffic => match self.traffic.as_mut() {
+                Some(traffic) => {
+                    traffic.rx = 0;
+                    traffic.tx = 0;
+                }
+                None => self.notice = Some("Traffic counters are off (--traffic)".to_string()),
+            },
+            Action::ClearBoth => {
+                for id in Scope::Both.panes(self.routes.len()) {
+                    self.clear(id);
+                }
+            }
+            Action::CopyPortCommands => self.copy_port_commands(),
+            Action::CancelTransfer => self.cancel_transfer(),
+            Action::FollowBoth => {
+                for id in Scope::Both.panes(self.routes.len()) {
+                    self.pane_mut(id).follow();
+                }
+            }
+        }
+    }
+
+    /// Give the main pane `percent` of the height, within `SPLIT_RANGE`,
+    /// and the error pane the rest.
+    pub fn set_split(&mut self, percent: u16) {
+        let percent = percent.clamp(*SPLIT_RANGE.start(), *SPLIT_RANGE.end());
+        self.top_perc = usize::from(percent);
+        self.bot_perc = 100 - self.top_perc;
+    }
+
+    /// Apply `move_view` `times` times to the focused pane, or with the
+    /// scroll lock on to every shown pane, so they all move by the same
+    /// number of lines and each stops at its own ends.
+    fn navigate(&mut self, times: usize, move_view: fn(&mut Pane)) {
+        let panes = if self.scroll_lock {
+            self.shown_panes()
+        } else {
+            vec![self.focus]
+        };
+        for id in panes {
+            let pane = self.pane_mut(id);
+            for _ in 0..times {
+                move_view(pane);
+            }
+        }
+    }
+
+    /// Lines PageUp and PageDown move: the focused pane's height, which
+    /// locked panes move by too.
+    fn page_height(&self) -> usize {
+        self.pane(self.focus).height.max(1)
+    }
+
+    /// The main pane, the routed panes and the error and warning panes
+    /// that are shown.
+    fn shown_panes(&self) -> Vec<PaneId> {
+        let mut panes = vec![PaneId::Main];
+        panes.extend((0..self.routes.len()).map(PaneId::Route));
+        if self.classify {
+            panes.push(PaneId::ErrorWarn);
+        }
+        if self.split_warnings {
+            panes.push(PaneId::Warnings);
+        }
+        panes
+    }
+
+    fn toggle_scroll_lock(&mut self) {
+        self.scroll_lock = !self.scroll_lock;
+        self.notice = Some(if self.scroll_lock {
+            "Scroll lock on: navigation keys move every pane".to_string()
+        } else {
+            "Scroll lock off".to_string()
+        });
+    }
+
+    /// Move the view of a pane by the scroll step.
+    fn scroll(&mut self, id: PaneId, down: bool) {
+        let step = self.scroll_step;
+        let pane = self.pane_mut(id);
+        for _ in 0..step {
+            if down {
+                pane.scroll_down();
+            } else {
+                pane.scroll_up();
+            }
+        }
+    }
+
+    fn set_scroll_step(&mut self, step: usize) {
+        self.scroll_step = step.max(1);
+        let unit = if self.scroll_step == 1 {
+            "line"
+        } else {
+            "lines"
+        };
+        self.notice = Some(format!("Scroll step: {} {}", self.scroll_step, unit));
+    }
+
+    /// Stop or resume reading from the port altogether.
+    fn toggle_read_pause(&mut self) {
+        match &self.reader {
+            Some(reader) => reader.set_paused(!reader.is_paused()),
+            None => self.notice = Some("Not reading from a port".to_string()),
+        }
+    }
+
+    /// Flash the main pane's border, and do whatever else `[success]` and
+    /// `--exit-on` ask for, if `line` means success.
+    fn check_success(&mut self, line: &str) {
+        let Some(success) = &self.success else {
+            return;
+        };
+        if !success.is_match(line) {
+            return;
+        }
+        self.success_flash = Some(Instant::now());
+        if success.bell {
+            let _ = bell::ring();
+        }
+        if success.pause {
+            self.ui_paused = true;
+        }
+        if success.exits(line) {
+            self.exit_line = Some(line.to_string());
+            self.quit = true;
+        }
+    }
+
+    /// Whether the main pane's border is green for a recent success line.
+    pub fn success_flashing(&self) -> bool {
+        self.success_flash
+            .is_some_and(|flash| flash.elapsed() < success::FLASH)
+    }
+
+    /// Pause or resume a replay; live ports keep `P` for that.
+    fn toggle_replay_pause(&mut self) {
+        match self.replay() {
+            Some(reader) => reader.set_paused(!reader.is_paused()),
+            None => self.notice = Some("Not replaying a session".to_string()),
+        }
+    }
+
+    /// Move a replay to the next faster or slower of `REPLAY_SPEEDS`.
+    fn step_replay_speed(&mut self, faster: bool) {
+        let Some(reader) = self.replay() else {
+            self.notice = Some("Not replaying a session".to_string());
+            return;
+        };
+        let speed = reader.replay_speed().unwrap_or(1.0);
+        let next = if faster {
+            REPLAY_SPEEDS.iter().find(|&&step| step > speed)
+        } else {
+            REPLAY_SPEEDS.iter().rev().find(|&&step| step < speed)
+        };
+        match next {
+            Some(&next) => reader.set_replay_speed(next),
+            None => {
+                self.notice = Some(format!(
+                    "Replay is already at its {} speed",
+                    if faster { "highest" } else { "lowest" }
+                ))
+            }
+        }
+    }
+
+    /// The reader, when it is playing back a recorded session.
+    fn replay(&self) -> Option<&ReaderControl> {
+        self.reader
+            .as_ref()
+            .filter(|reader| reader.replay_speed().is_some())
+    }
+
+    /// Switch lines received from now on to the next of UTF-8, Latin-1 and
+    /// CP437.
+    fn cycle_decoding(&mut self) {
+        let Some(reader) = &self.reader else {
+            self.notice = Some("Not reading from a port".to_string());
+            return;
+        };
+        let next = match reader.decoding() {
+            Decoding::Lossy | Decoding::Escape => Decoding::Latin1,
+            Decoding::Latin1 => Decoding::Cp437,
+            Decoding::Cp437 => self.utf8,
+        };
+        reader.set_decoding(next);
+    }
+
+    /// Tag naming the decoding in use, empty for UTF-8.
+    pub fn decoding_label(&self) -> &'static str {
+        self.reader
+            .as_ref()
+            .map_or("", |reader| reader.decoding().label())
+    }
+
+    /// Tag shown in the main pane title while lines arrive faster than the
+    /// UI takes them in, unless that is because the UI is paused.
+    pub fn lag_label(&self) -> String {
+        if self.ui_paused || self.backlog <= LAG_THRESHOLD {
+            return String::new();
+        }
+        format!(" [LAGGING: {} queued]", self.backlog)
+    }
+
+    /// Tags shown in the main pane title while either pause or inspect mode
+    /// is on.
+    pub fn pause_label(&self) -> String {
+        let mut label = String::new();
+        if self.read_only {
+            label.push_str(" [READ-ONLY]");
+        }
+        if self.inspect_mode.is_some() {
+            label.push_str(" [INSPECT]");
+        }
+        if self.scroll_lock {
+            label.push_str(" [SCROLL LOCK]");
+        }
+        if self.recording.is_some() {
+            label.push_str(" [RECORDING MACRO]");
+        }
+        if self.ui_paused {
+            label.push_str(" [UI PAUSED]");
+        }
+        if let Some(reader) = self.replay() {
+            let speed = reader.replay_speed().unwrap_or(1.0);
+            if reader.is_paused() {
+                label.push_str(&format!(" [REPLAY {}x PAUSED]", speed));
+            } else {
+                label.push_str(&format!(" [REPLAY {}x]", speed));
+            }
+        } else if self
+            .reader
+            .as_ref()
+            .is_some_and(|reader| reader.is_paused())
+        {
+            label.push_str(" [READ PAUSED]");
+        }
+        label
+    }
+
+    /// Empty one pane, keeping line numbers counting on. The other pane's
+    /// lines and scroll position are left alone.
+    fn clear(&mut self, id: PaneId) {
+        let pane = self.pane_mut(id);
+        pane.drop_oldest(pane.lines.len());
+        pane.follow();
+        // Positions in the other pane stay valid
+        if self.search_hit.is_some_and(|(pane, _)| pane == id) {
+            self.search_hit = None;
+        }
+        match id {
+            PaneId::Main => {
+                self.live = None;
+                if let Some(traffic) = self.traffic.as_mut().filter(|traffic| !traffic.cumulative) {
+                    traffic.rx = 0;
+                    traffic.tx = 0;
+                }
+            }
+            PaneId::ErrorWarn => self.error_cursor = None,
+            PaneId::Warnings | PaneId::Route(_) => {}
+        }
+    }
+
+    /// Move the error pane cursor to the next or previous entry, keeping it
+    /// in view.
+    fn step_error(&mut self, forward: bool) {
+        let pane = &mut self.error_warn;
+        if pane.lines.is_empty() {
+            self.notice = Some("No errors or warnings".to_string());
+            return;
+        }
+        let first = pane.trimmed;
+        let last = pane.trimmed + pane.lines.len() - 1;
+        // Start over from an end if the entry has been trimmed away
+        let cursor = match self.error_cursor.filter(|&cursor| cursor >= first) {
+            None if forward => first,
+            None => last,
+            Some(cursor) if forward => (cursor + 1).min(last),
+            Some(cursor) => cursor.saturating_sub(1).max(first),
+        };
+        self.error_cursor = Some(cursor);
+        pane.center(cursor - first);
+    }
+
+    /// Position of the error pane cursor, for the error pane title.
+    pub fn error_cursor_label(&self) -> String {
+        let trimmed = self.error_warn.trimmed;
+        match self.error_cursor.filter(|&cursor| cursor >= trimmed) {
+            Some(cursor) => format!(
+                " [error {} of {}]",
+                cursor - trimmed + 1,
+                self.error_warn.lines.len()
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Center the newest error in the error/warning pane, and the main pane
+    /// on what arrived around the same time.
+    fn jump_to_last_error(&mut self) {
+        let found = self
+            .error_warn
+            .lines
+            .iter()
+            .rposition(|line| line.severity == Some(Severity::Error));
+        let Some(index) = found else {
+            self.notice = Some("No errors".to_string());
+            return;
+        };
+        self.error_warn.center(index);
+
+        // Last main line that arrived no later than the error
+        let time = self.error_warn.lines[index].time;
+        let context = self.main.lines.partition_point(|line| line.time <= time);
+        if context > 0 {
+            self.main.center(context - 1);
+        }
+    }
+
+    /// Copy the whole error/warning pane to the clipboard.
+    fn copy_error_warn(&mut self) {
+        let text = self
+            .error_warn
+            .lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let count = self.error_warn.lines.len();
+        self.notice = Some(match clipboard::copy(&text) {
+            Ok(()) => format!("Copied {} error/warning lines to the clipboard", count),
+            Err(e) => format!("Copy failed: {}", e),
+        });
+    }
+
+    /// Freeze or unfreeze the errors, and the warnings with them when they
+    /// have their own pane, so both hold still together.
+    fn toggle_error_freeze(&mut self) {
+        self.error_warn.toggle_freeze();
+        if self.split_warnings && self.warnings.frozen.is_some() != self.error_warn.frozen.is_some()
+        {
+            self.warnings.toggle_freeze();
+        }
+    }
+
+    /// Enter or leave inspect mode. Entering freezes every pane, shows line
+    /// numbers and byte counts, starts visual mode and turns sending off;
+    /// leaving undoes what entering changed.
+    fn toggle_inspect_mode(&mut self) {
+        if let Some(mode) = self.inspect_mode.take() {
+            self.display.line_numbers = mode.line_numbers;
+            self.display.byte_counts = mode.byte_counts;
+            // Panes thawed by hand meanwhile stay as they are
+            for id in mode.froze {
+                let pane = self.pane_mut(id);
+                if pane.frozen.is_some() {
+                    pane.toggle_freeze();
+                }
+            }
+            self.selection = None;
+            return;
+        }
+        let mut froze = Vec::new();
+        for id in Scope::Both.panes(self.routes.len()) {
+            let pane = self.pane_mut(id);
+            if pane.frozen.is_none() {
+                pane.toggle_freeze();
+                froze.push(id);
+            }
+        }
+        self.inspect_mode = Some(InspectMode {
+            line_numbers: self.display.line_numbers,
+            byte_counts: self.display.byte_counts,
+            froze,
+        });
+        self.display.line_numbers = true;
+        self.display.byte_counts = true;
+        self.start_selection();
+    }
+
+    /// Copy command lines for screen, minicom and picocom that open the port
+    /// with the settings in use, and show them.
+    fn copy_port_commands(&mut self) {
+        let Some(writer) = self.writer.as_ref() else {
+            self.notice = Some("Not connected".to_string());
+            return;
+        };
+        let commands = match PortSettings::of(writer.as_ref()) {
+            Ok(settings) => settings.commands(),
+            Err(e) => {
+                self.notice = Some(format!("Cannot read the port settings: {}", e));
+                return;
+            }
+        };
+        self.notice = Some(match clipboard::copy(&commands.join("\n")) {
+            Ok(()) => "Copied the commands to the clipboard".to_string(),
+            Err(e) => format!("Copy failed: {}", e),
+        });
+        self.port_commands = Some(commands);
+    }
+
+    /// Enter visual mode with the cursor on the bottom visible line of the
+    /// focused pane.
+    fn start_selection(&mut self) {
+        let focus = self.focus;
+        let pane = self.pane_mut(focus);
+        if pane.lines.is_empty() {
+            return;
+        }
+        let bottom = (pane.scroll_offset + pane.height.max(1) - 1).min(pane.lines.len() - 1);
+        let line = pane.trimmed + bottom;
+        pane.reveal(bottom);
+        self.selection = Some(Selection {
+            pane: focus,
+            anchor: line,
+            cursor: line,
+        });
+    }
+
+    fn handle_visual_key(&mut self, key: KeyEvent) {
+        let Some(mut selection) = self.selection else {
+            return;
+        };
+        let pane = self.pane(selection.pane);
+        let first = pane.trimmed;
+        let last = first + pane.lines.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('v') => {
+                self.selection = None;
+                return;
+            }
+            KeyCode::Char('I') if self.inspect_mode.is_some() => {
+                self.toggle_inspect_mode();
+                return;
+            }
+            KeyCode::Up => selection.cursor = selection.cursor.saturating_sub(1),
+            KeyCode::Down => selection.cursor += 1,
+            KeyCode::PageUp => selection.cursor = selection.cursor.saturating_sub(pane.height),
+            KeyCode::PageDown => selection.cursor += pane.height,
+            KeyCode::Char('y') => {
+                let text = self.selected_text();
+                let count = text.lines().count();
+                self.notice = Some(match clipboard::copy(&text) {
+                    Ok(()) => format!("Copied {} lines to the clipboard", count),
+                    Err(e) => format!("Copy failed: {}", e),
+                });
+                self.selection = None;
+                return;
+            }
+            KeyCode::Char('f') => {
+                self.open_prompt(PromptKind::SaveSelection);
+                return;
+            }
+            KeyCode::Char('i') => {
+                self.inspect(selection.pane, selection.cursor);
+                return;
+            }
+            _ => {}
+        }
+        // Keep the cursor in view as it moves, even past trimmed lines
+        selection.cursor = selection.cursor.clamp(first, last);
+        self.pane_mut(selection.pane)
+            .reveal(selection.cursor - first);
+        self.selection = Some(selection);
+    }
+
+    /// Open absolute line `line` of `pane` in the byte inspector.
+    fn inspect(&mut self, pane: PaneId, line: usize) {
+        let pane = self.pane(pane);
+        let Some(entry) = line
+            .checked_sub(pane.trimmed)
+            .and_then(|index| pane.lines.get(index))
+        else {
+            return;
+        };
+        self.inspection = Some(Inspection {
+            number: line + 1,
+            text: entry.text.clone(),
+            raw: entry.raw.clone(),
+        });
+    }
+
+    /// Lines covered by the visual selection, joined with newlines.
+    fn selected_text(&self) -> String {
+        let Some(selection) = self.selection else {
+            return String::new();
+        };
+        let pane = self.pane(selection.pane);
+        let (first, last) = selection.range();
+        let start = first.saturating_sub(pane.trimmed);
+        let end = (last + 1).saturating_sub(pane.trimmed);
+        pane.lines[start..end.min(pane.lines.len())]
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn save_selection(&mut self, path: &str) {
+        let text = self.selected_text();
+        let count = text.lines().count();
+        self.notice = Some(match fs::write(path.trim(), text + "\n") {
+            Ok(()) => format!("Saved {} lines to {}", count, path.trim()),
+            Err(e) => format!("Save failed: {}", e),
+        });
+        self.selection = None;
+    }
+
+    /// Text of the shown lines matching the search in the panes it looks
+    /// in, oldest first, so the lines of several panes interleave as they
+    /// arrived. Lines hidden before a mark are left out.
+    fn matching_text(&self) -> Vec<&str> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        let mut lines: Vec<_> = search
+            .scope
+            .panes(self.routes.len())
+            .into_iter()
+            .flat_map(|id| {
+                let pane = self.pane(id);
+                pane.lines[pane.first_shown()..].iter()
+            })
+            .filter(|line| search.is_match(&line.text))
+            .collect();
+        // Stable, so lines of one pane keep their order
+        lines.sort_by_key(|line| line.time);
+        lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    fn save_matches(&mut self, path: &str) {
+        let lines = self.matching_text();
+        if lines.is_empty() {
+            self.notice = Some("No lines match the search".to_string());
+            return;
+        }
+        let count = lines.len();
+        self.notice = Some(match fs::write(path.trim(), lines.join("\n") + "\n") {
+            Ok(()) => format!("Saved {} matching lines to {}", count, path.trim()),
+            Err(e) => format!("Save failed: {}", e),
+        });
+    }
+
+    /// Label in front of the prompt input, with the case indicator when
+    /// searching.
+    pub fn prompt_label(&self, kind: PromptKind) -> String {
+        match kind {
+            PromptKind::Search => format!(
+                "Search [{} {}]: ",
+                search::case_label(self.case_sensitive),
+                self.search_scope.label()
+            ),
+            kind => kind.label().to_string(),
+        }
+    }
+
+    /// Apply changed search settings to the search in progress.
+    fn refresh_search(&mut self) {
+        if let Some(search) = &self.search {
+            self.search = Some(Search::new(
+                &search.query,
+                self.case_sensitive,
+                self.search_scope,
+            ));
+        }
+    }
+
+    /// Search the panes in scope for the text typed at the prompt, or clear
+    /// the search if it is empty.
+    fn start_search(&mut self, input: &str) {
+        self.search_hit = None;
+        if input.is_empty() {
+            self.search = None;
+            return;
+        }
+        self.search = Some(Search::new(input, self.case_sensitive, self.search_scope));
+        self.find(true);
+    }
+
+    /// Move to the next (or previous) line matching the search, through the
+    /// main pane and then the error pane when both are in scope, wrapping
+    /// around at the ends.
+    fn find(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            self.notice = Some("No search; press / to start one".to_string());
+            return;
+        };
+        // The panes in scope, one after the other, as a single list of lines
+        let panes = search.scope.panes(self.routes.len());
+        let counts: Vec<usize> = panes.iter().map(|&id| self.pane(id).lines.len()).collect();
+        let start = |n: usize| counts[..n].iter().sum::<usize>() as isize;
+        let total = start(panes.len());
+        if total == 0 {
+            return;
+        }
+        let locate = |i: isize| {
+            let mut index = i.rem_euclid(total) as usize;
+            let mut n = 0;
+            while index >= counts[n] {
+                index -= counts[n];
+                n += 1;
+            }
+            (n, index)
+        };
+
+        // Continue past the current match, or from the edge of the view
+        let step = if forward { 1 } else { -1 };
+        let current = self
+            .search_hit
+            .and_then(|(id, line)| Some((panes.iter().position(|&pane| pane == id)?, line)));
+        let from = match current {
+            Some((n, line)) => {
+                start(n) + line as isize - self.pane(panes[n]).trimmed as isize + step
+            }
+            None => {
+                let n = panes
+                    .iter()
+                    .position(|&pane| pane == self.focus)
+                    .unwrap_or(0);
+                let pane = self.pane(panes[n]);
+                let edge = if forward {
+                    pane.scroll_offset as isize
+                } else {
+                    ((pane.scroll_offset + pane.height.max(1)) as isize - 1)
+                        .min(counts[n] as isize - 1)
+                };
+                start(n) + edge
+            }
+        }
+        .clamp(-1, total);
+        let found = (0..total).map(|k| from + k * step).find(|&i| {
+            let (n, index) = locate(i);
+            search.is_match(&self.pane(panes[n]).lines[index].text)
+        });
+        let Some(i) = found else {
+            self.notice = Some(format!("Not found: {}", search.query));
+            return;
+        };
+        if !(0..total).contains(&i) {
+            self.notice = Some("Search wrapped".to_string());
+        }
+        let (n, index) = locate(i);
+        let id = panes[n];
+        let pane = self.pane_mut(id);
+        pane.reveal(index);
+        self.search_hit = Some((id, pane.trimmed + index));
+        self.focus = id;
+    }
+
+    /// Take in text pasted into the terminal. In the send prompt every
+    /// complete line is sent, as if typed and followed by Enter, and the text
+    /// after the last line break stays in the input.
+    pub fn handle_paste(&mut self, text: String) {
+        // Commands are single keys, so a paste outside a prompt is never one
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if prompt.kind != PromptKind::Send {
+            prompt
+                .input
+                .push_str(&text.trim_end_matches('\n').replace('\n', " "));
+            return;
+        }
+        let Some((lines, rest)) = text.rsplit_once('\n') else {
+            prompt.input.push_str(&text);
+            return;
+        };
+        let typed = mem::replace(&mut prompt.input, rest.to_string());
+        for (i, line) in lines.split('\n').enumerate() {
+            if i == 0 {
+                self.send_typed(format!("{}{}", typed, line));
+            } else {
+                self.send_typed(line.to_string());
+            }
+        }
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind) {
+        self.prompt = Some(Prompt {
+            kind,
+            input: String::new(),
+        });
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        // Alt+C flips case sensitivity and Alt+S cycles the scope while searching
+        if prompt.kind == PromptKind::Search && key.modifiers.contains(KeyModifiers::ALT) {
+            match key.code {
+                KeyCode::Char('c') => self.case_sensitive = !self.case_sensitive,
+                KeyCode::Char('s') => self.search_scope = self.search_scope.next(),
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Esc => {
+                let prompt = self.prompt.take();
+                if prompt.is_some_and(|prompt| prompt.kind == PromptKind::SaveMacro) {
+                    self.discard_macro();
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    match prompt.kind {
+                        PromptKind::Mark => self.mark(&prompt.input),
+                        PromptKind::Send => self.send_typed(prompt.input),
+                        PromptKind::SaveSelection => self.save_selection(&prompt.input),
+                        PromptKind::SaveMatches => self.save_matches(&prompt.input),
+                        PromptKind::SaveMacro => self.save_macro(&prompt.input),
+                        PromptKind::Baud => self.set_baud_rate(&prompt.input),
+                        PromptKind::GotoLine => self.goto_line(&prompt.input),
+                        PromptKind::SaveRules => self.save_rules(&prompt.input),
+                        PromptKind::LoadRules => self.load_rules(&prompt.input),
+                        PromptKind::SendFile => self.send_file(&prompt.input),
+                        PromptKind::Search => self.start_search(&prompt.input),
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An app with both panes full and scrolled back from the newest line.
+    fn scrolled_app() -> App {
+        let mut app = App::new();
+        for i in 0..20 {
+            app.main.push(format!("line {}", i), Color::Reset);
+            app.error_warn.push(format!("error {}", i), Color::Red);
+        }
+        for pane in [&mut app.main, &mut app.error_warn] {
+            pane.scroll_offset = 5;
+            pane.is_scrolled = true;
+        }
+        app
+    }
+
+    #[test]
+    fn clearing_main_leaves_the_error_pane_alone() {
+        let mut app = scrolled_app();
+        app.search_hit = Some((PaneId::ErrorWarn, 7));
+        app.error_cursor = Some(7);
+        app.clear(PaneId::Main);
+
+        assert!(app.main.lines.is_empty());
+        assert!(!app.main.is_scrolled);
+        assert_eq!(app.error_warn.lines.len(), 20);
+        assert_eq!(app.error_warn.scroll_offset, 5);
+        assert!(app.error_warn.is_scrolled);
+        assert_eq!(app.search_hit, Some((PaneId::ErrorWarn, 7)));
+        assert_eq!(app.error_cursor, Some(7));
+    }
+
+    #[test]
+    fn clearing_errors_leaves_the_main_pane_alone() {
+        let mut app = scrolled_app();
+        app.search_hit = Some((PaneId::Main, 7));
+        app.error_cursor = Some(7);
+        app.clear(PaneId::ErrorWarn);
+
+        assert!(app.error_warn.lines.is_empty());
+        assert!(!app.error_warn.is_scrolled);
+        assert_eq!(app.error_cursor, None);
+        assert_eq!(app.main.lines.len(), 20);
+        assert_eq!(app.main.scroll_offset, 5);
+        assert!(app.main.is_scrolled);
+        assert_eq!(app.search_hit, Some((PaneId::Main, 7)));
+    }
+
+    #[test]
+    fn live_line_is_updated_in_place_past_sent_lines() {
+        let mut app = App::new();
+        app.handle_message(Message::Update(Payload::rx("loading 10%".to_string())));
+        let mut sent = Payload::rx("AT".to_string());
+        sent.direction = Direction::Tx;
+        app.handle_message(Message::Line(sent));
+        app.handle_message(Message::Update(Payload::rx("loading 50%".to_string())));
+
+        let texts = |app: &App| {
+            app.main
+                .lines
+                .iter()
+                .map(|line| line.text.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts(&app), ["loading 50%", "AT"]);
+        assert_eq!(app.live_text(), Some("loading 50%"));
+        assert_eq!(app.progress, Some(50));
+
+        app.handle_message(Message::Line(Payload::rx("loading done".to_string())));
+        assert_eq!(texts(&app), ["AT", "loading done"]);
+        assert_eq!(app.live_text(), None);
+    }
+}
@@ -0,0 +1,121 @@
+use std::io;
+
+/// Baud rates that virtually every driver accepts.
+pub const STANDARD_RATES: &[u32] = &[
+    300, 600, 1200, 2400, 4800, 9600, 14400, 19200, 38400, 57600, 115200, 230400, 460800, 500000,
+    921600, 1000000, 1500000, 2000000, 3000000,
+];
+
+/// The standard rate closest to `rate`.
+pub fn nearest_standard(rate: u32) -> u32 {
+    STANDARD_RATES
+        .iter()
+        .copied()
+        .min_by_key(|&standard| standard.abs_diff(rate))
+        .unwrap_or(115200)
+}
+
+/// The open baud rate menu: the standard rates, with the current one among
+/// them, and last an entry for typing any other rate.
+#[derive(Clone, Debug)]
+pub struct BaudMenu {
+    pub rates: Vec<u32>,
+    pub selected: usize, // Index into `rates`, or `rates.len()` for the custom entry
+}
+
+impl BaudMenu {
+    /// The menu with `current` selected, listed in order if it is not a
+    /// standard rate.
+    pub fn new(current: u32) -> Self {
+        let mut rates = STANDARD_RATES.to_vec();
+        let selected = match rates.binary_search(&current) {
+            Ok(i) => i,
+            Err(i) => {
+                rates.insert(i, current);
+                i
+            }
+        };
+        BaudMenu { rates, selected }
+    }
+
+    /// The selected rate, or `None` for the custom entry.
+    pub fn choice(&self) -> Option<u32> {
+        self.rates.get(self.selected).copied()
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.rates.len());
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
+
+/// Whether `err` looks like the driver rejecting the baud rate itself.
+/// Other failures, such as a busy port or a missing permission, say nothing
+/// about the rate, whatever it is.
+pub fn is_rate_rejected(err: &serialport::Error) -> bool {
+    matches!(
+        err.kind(),
+        serialport::ErrorKind::InvalidInput
+            | serialport::ErrorKind::Io(io::ErrorKind::InvalidInput)
+    )
+}
+
+/// Turn a failure to open the port or set `rate` into a message that says
+/// what to try instead.
+pub fn describe_error(rate: u32, err: &serialport::Error) -> String {
+    if !is_rate_rejected(err) {
+        return err.to_string();
+    }
+    let nearest = nearest_standard(rate);
+    if nearest == rate {
+        format!("Baud rate {} was rejected by the driver: {}", rate, err)
+    } else {
+        format!(
+            "Baud rate {} is not supported on this platform or driver ({}); the nearest standard rate is {}",
+            rate, err, nearest
+        )
+    }
+}
+
+/// Whether [`set_custom_rate`] can set arbitrary rates on this platform.
+pub const CUSTOM_RATES: bool = cfg!(all(
+    target_os = "linux",
+    not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+));
+
+/// Set any `rate` on an open port with the termios2 `BOTHER` flag, for
+/// drivers that accept rates serialport cannot set itself.
+#[cfg(all(
+    target_os = "linux",
+    not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+))]
+pub fn set_custom_rate(port: &impl std::os::fd::AsRawFd, rate: u32) -> io::Result<()> {
+    let fd = port.as_raw_fd();
+    // SAFETY: termios2 is plain data and the ioctls only read or write it
+    unsafe {
+        let mut tio: libc::termios2 = std::mem::zeroed();
+        if libc::ioctl(fd, libc::TCGETS2, &mut tio) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Same rate in both directions
+        tio.c_cflag &= !(libc::CBAUD | (libc::CBAUD << libc::IBSHIFT));
+        tio.c_cflag |= libc::BOTHER | (libc::BOTHER << libc::IBSHIFT);
+        tio.c_ispeed = rate;
+        tio.c_ospeed = rate;
+        if libc::ioctl(fd, libc::TCSETS2, &tio) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+)))]
+pub fn set_custom_rate<T>(_port: &T, _rate: u32) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,46 @@
+use crate::config::BellConfig;
+use regex::RegexSet;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Default time after a bell during which further matches stay silent.
+const QUIET: Duration = Duration::from_secs(5);
+
+/// Rings the terminal bell when a received line matches one of the
+/// patterns of the `[bell]` config section.
+pub struct Bell {
+    patterns: RegexSet,
+    quiet: Duration,       // Matches this soon after a bell do not ring again
+    rung: Option<Instant>, // When the bell last rang
+}
+
+impl Bell {
+    pub fn from_config(config: &BellConfig) -> Result<Self, String> {
+        let patterns = RegexSet::new(&config.patterns)
+            .map_err(|e| format!("Invalid pattern in [bell]: {}", e))?;
+        Ok(Bell {
+            patterns,
+            quiet: config.quiet_ms.map_or(QUIET, Duration::from_millis),
+            rung: None,
+        })
+    }
+
+    /// Ring if `line` matches and the bell has been quiet long enough, so a
+    /// flood of matching lines rings once.
+    pub fn check(&mut self, line: &str) -> io::Result<()> {
+        if !self.patterns.is_match(line)
+            || self.rung.is_some_and(|rung| rung.elapsed() < self.quiet)
+        {
+            return Ok(());
+        }
+        self.rung = Some(Instant::now());
+        ring()
+    }
+}
+
+/// Ring the terminal bell.
+pub fn ring() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x07")?;
+    stdout.flush()
+}
@@ -0,0 +1,105 @@
+use crate::app::App;
+use crate::demo;
+use crate::line::{LineSplitter, MessageReceiver, MessageSender, ReaderControl};
+use crate::ui;
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long lines are fed for, with and again without the wait for input.
+const RUN_TIME: Duration = Duration::from_secs(3);
+
+/// Columns and rows of the offscreen terminal frames are drawn to.
+const SIZE: (u16, u16) = (120, 40);
+
+/// Bytes of demo lines split at a time, as if read from a port.
+const CHUNK: usize = 4096;
+
+/// Spawn a thread that splits demo lines into messages for `tx` as the
+/// reader thread does, keeping `per_frame` or more of them queued so the UI
+/// never waits for lines.
+pub fn spawn(per_frame: usize, control: ReaderControl, tx: MessageSender) {
+    thread::spawn(move || {
+        let mut splitter = LineSplitter::new(control);
+        let mut number: u64 = 0;
+        let mut chunk = Vec::with_capacity(CHUNK + 256);
+        loop {
+            if tx.queued() >= 2 * per_frame {
+                thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            chunk.clear();
+            while chunk.len() < CHUNK {
+                number += 1;
+                chunk.extend_from_slice(demo::line(number).as_bytes());
+                chunk.extend_from_slice(b"\r\n");
+            }
+            for message in splitter.feed(&chunk) {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Take the lines `spawn` sends from `rx` into `app`, at most `per_frame`
+/// of them before drawing each frame offscreen, as the main loop does. Then
+/// print the line rate the UI kept up with.
+///
+/// Without input the main loop waits `frame_wait` before each frame, which
+/// bounds the sustained rate; the rate measured without that wait is
+/// printed too, as it tracks the cost of ingesting and drawing alone.
+pub fn run(
+    app: &mut App,
+    rx: &mut MessageReceiver,
+    per_frame: usize,
+    frame_wait: Duration,
+) -> io::Result<()> {
+    let mut terminal = Terminal::new(TestBackend::new(SIZE.0, SIZE.1))?;
+    let (lines, elapsed, _) = measure(app, &mut terminal, rx, per_frame, frame_wait)?;
+    let sustained = lines as f64 / elapsed.as_secs_f64();
+    let (lines, elapsed, frames) = measure(app, &mut terminal, rx, per_frame, Duration::ZERO)?;
+    let frame = elapsed / frames.max(1);
+    let unthrottled = lines as f64 / elapsed.as_secs_f64();
+    println!(
+        "{:.0} lines/s sustained ({:.2} ms per frame of {} lines at {}x{}; {:.0} lines/s without the {} ms wait for input)",
+        sustained,
+        frame.as_secs_f64() * 1000.0,
+        per_frame,
+        SIZE.0,
+        SIZE.1,
+        unthrottled,
+        frame_wait.as_millis()
+    );
+    Ok(())
+}
+
+/// Run the main loop for `RUN_TIME`, returning the lines taken in, the
+/// time taken and the frames drawn.
+fn measure(
+    app: &mut App,
+    terminal: &mut Terminal<TestBackend>,
+    rx: &mut MessageReceiver,
+    per_frame: usize,
+    frame_wait: Duration,
+) -> io::Result<(usize, Duration, u32)> {
+    let started = Instant::now();
+    let (mut lines, mut frames) = (0, 0);
+    while started.elapsed() < RUN_TIME {
+        // Stands in for waiting on input that does not come
+        thread::sleep(frame_wait);
+        for _ in 0..per_frame {
+            let Some(message) = rx.try_recv() else {
+                break;
+            };
+            lines += 1;
+            app.handle_message(message);
+        }
+        terminal.draw(|f| ui::draw(f, app))?;
+        frames += 1;
+    }
+    Ok((lines, started.elapsed(), frames))
+}
@@ -0,0 +1,138 @@
+use crate::checksum::Checksum;
+use crate::classify::{Classifier, Severity};
+use crate::line::{Message, MessageReceiver};
+use crate::logfile::Logger;
+use crate::skip::Skipper;
+use crate::success::Success;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to sleep when no line is waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Set by the SIGINT handler to end the capture cleanly.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Write every line from `rx` to `log` without a terminal UI, until
+/// `duration` has passed, Ctrl-C is pressed, the port is gone for good or a
+/// line matches a `success` pattern that quits. Prints a one-line summary at
+/// the end.
+///
+/// Returns whether the capture got what it waited for, which is false when
+/// a line was meant to end it but none did.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    path: &Path,
+    mut log: Logger,
+    mut rx: MessageReceiver,
+    classifier: &Classifier,
+    checksum: Option<&Checksum>,
+    success: Option<&Success>,
+    mut skip: Option<Skipper>,
+    duration: Option<Duration>,
+) -> io::Result<bool> {
+    catch_interrupt();
+    let started = Instant::now();
+    let (mut lines, mut errors, mut warnings) = (0, 0, 0);
+    let mut exit_line = None;
+    while !INTERRUPTED.load(Ordering::Relaxed)
+        && duration.is_none_or(|duration| started.elapsed() < duration)
+    {
+        let Some(message) = rx.try_recv() else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        match message {
+            Message::Line(payload) => {
+                if let Some(skip) = skip.as_mut() {
+                    if skip.skip_line() {
+                        continue;
+                    }
+                    if let Some(note) = skip.note() {
+                        eprintln!("{}", note);
+                    }
+                }
+                log.write_from(&payload.text, payload.source)?;
+                lines += 1;
+                if checksum.is_some_and(|checksum| checksum.check(&payload.text).is_some()) {
+                    errors += 1;
+                } else {
+                    match classifier.classify(&payload).map(|class| class.severity) {
+                        Some(Severity::Error) => errors += 1,
+                        Some(Severity::Warning) => warnings += 1,
+                        None => {}
+                    }
+                }
+                if success.is_some_and(|success| success.exits(&payload.text)) {
+                    exit_line = Some(payload.text);
+                    break;
+                }
+            }
+            // Only the finished line is captured
+            Message::Update(_) => {}
+            // Nothing is sent during a capture
+            Message::Prompt => {}
+            Message::Disconnected(reason) => eprintln!("Disconnected: {}", reason),
+            Message::Reconnecting { attempt, max: 0 } => {
+                eprintln!("Reconnect attempt {}", attempt)
+            }
+            Message::Reconnecting { attempt, max } => {
+                eprintln!("Reconnect attempt {}/{}", attempt, max)
+            }
+            Message::Reconnected(_) => {
+                eprintln!("Reconnected");
+                if let Some(skip) = skip.as_mut() {
+                    skip.restart();
+                }
+            }
+            Message::GaveUp => {
+                eprintln!("Giving up: could not reconnect to the port");
+                break;
+            }
+            Message::Error(error) => eprintln!("{}", error),
+            Message::Ended => break,
+        }
+    }
+    log.end(vec![
+        ("errors", errors.to_string()),
+        ("warnings", warnings.to_string()),
+    ])?;
+    println!(
+        "Captured {} lines ({} errors, {} warnings) in {:.1}s to {}",
+        lines,
+        errors,
+        warnings,
+        started.elapsed().as_secs_f64(),
+        path.display()
+    );
+    match exit_line {
+        Some(line) => println!("Success: {}", line),
+        None if success.is_some_and(Success::quits) => {
+            eprintln!("No line matched the success pattern");
+            return Ok(false);
+        }
+        None => {}
+    }
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn catch_interrupt() {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::Relaxed);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+/// Ctrl-C ends the process; lines are already flushed one by one.
+#[cfg(not(unix))]
+fn catch_interrupt() {}
@@ -0,0 +1,140 @@
+use crate::config::ChecksumConfig;
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt;
+
+/// Checksum algorithms a protocol may embed in its lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Algorithm {
+    /// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+    Crc16,
+    /// CRC-16/MODBUS: reflected polynomial 0xA001, initial value 0xFFFF.
+    Crc16Modbus,
+    /// The CRC-32 of zlib and Ethernet.
+    Crc32,
+}
+
+impl Algorithm {
+    pub fn compute(self, data: &[u8]) -> u32 {
+        match self {
+            Algorithm::Crc16 => {
+                let mut crc: u16 = 0xFFFF;
+                for &byte in data {
+                    crc ^= u16::from(byte) << 8;
+                    for _ in 0..8 {
+                        crc = if crc & 0x8000 != 0 {
+                            (crc << 1) ^ 0x1021
+                        } else {
+                            crc << 1
+                        };
+                    }
+                }
+                u32::from(crc)
+            }
+            Algorithm::Crc16Modbus => {
+                let mut crc: u16 = 0xFFFF;
+                for &byte in data {
+                    crc ^= u16::from(byte);
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 {
+                            (crc >> 1) ^ 0xA001
+                        } else {
+                            crc >> 1
+                        };
+                    }
+                }
+                u32::from(crc)
+            }
+            Algorithm::Crc32 => {
+                let mut crc: u32 = 0xFFFF_FFFF;
+                for &byte in data {
+                    crc ^= u32::from(byte);
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 {
+                            (crc >> 1) ^ 0xEDB8_8320
+                        } else {
+                            crc >> 1
+                        };
+                    }
+                }
+                !crc
+            }
+        }
+    }
+
+    /// Hex digits in a checksum of this kind.
+    fn digits(self) -> usize {
+        match self {
+            Algorithm::Crc16 | Algorithm::Crc16Modbus => 4,
+            Algorithm::Crc32 => 8,
+        }
+    }
+}
+
+/// Recomputes the checksum carried by received lines.
+pub struct Checksum {
+    regex: Regex,
+    algorithm: Algorithm,
+}
+
+/// A line whose checksum field does not match its payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub found: String,
+    pub expected: u32,
+    digits: usize,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "bad checksum {}, expected {:0width$X}",
+            self.found,
+            self.expected,
+            width = self.digits
+        )
+    }
+}
+
+impl Checksum {
+    pub fn from_config(config: &ChecksumConfig) -> Result<Self, String> {
+        let regex = Regex::new(&config.pattern)
+            .map_err(|e| format!("Invalid checksum pattern {:?}: {}", config.pattern, e))?;
+        let named = ["payload", "checksum"]
+            .iter()
+            .all(|name| regex.capture_names().any(|n| n == Some(name)));
+        if !named && regex.captures_len() < 3 {
+            return Err(format!(
+                "Checksum pattern {:?} needs a group for the payload and one for the checksum",
+                config.pattern
+            ));
+        }
+        Ok(Checksum {
+            regex,
+            algorithm: config.algorithm,
+        })
+    }
+
+    /// Check a line against the pattern's `payload` and `checksum` groups,
+    /// or its first and second groups when they are not named. Lines the
+    /// pattern does not match carry no checksum and pass.
+    pub fn check(&self, line: &str) -> Option<Mismatch> {
+        let captures = self.regex.captures(line)?;
+        let (payload, found) = match (captures.name("payload"), captures.name("checksum")) {
+            (Some(payload), Some(found)) => (payload, found),
+            _ => (captures.get(1)?, captures.get(2)?),
+        };
+        let expected = self.algorithm.compute(payload.as_str().as_bytes());
+        // An unreadable checksum field is as bad as a wrong one
+        if u32::from_str_radix(found.as_str(), 16) == Ok(expected) {
+            return None;
+        }
+        Some(Mismatch {
+            found: found.as_str().to_string(),
+            expected,
+            digits: self.algorithm.digits(),
+        })
+    }
+}
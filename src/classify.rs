@@ -0,0 +1,145 @@
+use crate::config::{ClassifyConfig, Precedence};
+use crate::line::{Direction, Payload};
+use ratatui::style::Color;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Severity of a line that belongs in the error/warning pane, lowest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// The class of a line with this severity in its usual color.
+    pub fn class(self) -> Class {
+        Class {
+            severity: self,
+            color: self.color(),
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Error => Color::Red,      // Red color for errors
+            Severity::Warning => Color::Yellow, // Yellow color for warnings
+        }
+    }
+}
+
+/// A compiled classification rule.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub regex: Regex,
+    pub severity: Severity,
+    pub color: Option<Color>, // Overrides the severity's colour
+}
+
+/// How a line was classified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Class {
+    pub severity: Severity,
+    pub color: Color,
+}
+
+/// Decides which lines are errors or warnings.
+#[derive(Clone, Debug)]
+pub struct Classifier {
+    pub rules: Vec<Rule>,
+    pub precedence: Precedence,
+}
+
+impl Classifier {
+    /// The words "ERR" or "ERROR" make a line an error, otherwise "WRN",
+    /// "WARN" or "WARNING" make it a warning, ignoring case.
+    ///
+    /// With `loose` the keywords match anywhere, even inside other words
+    /// ("ferry", "errorless").
+    pub fn builtin(loose: bool) -> Self {
+        let rule = |pattern: &str, severity| Rule {
+            regex: Regex::new(pattern).expect("built-in pattern is valid"),
+            severity,
+            color: None,
+        };
+        let rules = if loose {
+            vec![
+                rule("(?i)err", Severity::Error),
+                rule("(?i)wrn|warn", Severity::Warning),
+            ]
+        } else {
+            vec![
+                rule(r"(?i)\b(err|errors?)\b", Severity::Error),
+                rule(r"(?i)\b(wrn|warn|warnings?)\b", Severity::Warning),
+            ]
+        };
+        Classifier {
+            rules,
+            precedence: Precedence::First,
+        }
+    }
+
+    /// Compile configured rules, falling back to the built-in ones when there
+    /// are none.
+    pub fn compile(
+        rules: Option<&[ClassifyConfig]>,
+        precedence: Precedence,
+        loose_keywords: bool,
+    ) -> Result<Self, String> {
+        let Some(rules) = rules else {
+            return Ok(Classifier {
+                precedence,
+                ..Classifier::builtin(loose_keywords)
+            });
+        };
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let regex = Regex::new(&rule.pattern)
+                    .map_err(|e| format!("Invalid classify pattern {:?}: {}", rule.pattern, e))?;
+                let color = match &rule.color {
+                    Some(color) => Some(
+                        Color::from_str(color)
+                            .map_err(|_| format!("Invalid classify color {:?}", color))?,
+                    ),
+                    None => None,
+                };
+                Ok(Rule {
+                    regex,
+                    severity: rule.severity,
+                    color,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Classifier { rules, precedence })
+    }
+
+    /// Decide whether a line is an error, a warning or ordinary output.
+    ///
+    /// Lines we sent ourselves are never highlighted, so typing a command that
+    /// mentions "error" does not land it in the error pane.
+    pub fn classify(&self, payload: &Payload) -> Option<Class> {
+        if payload.direction == Direction::Tx {
+            return None;
+        }
+
+        let mut matches = self
+            .rules
+            .iter()
+            .filter(|rule| rule.regex.is_match(&payload.text));
+        let rule = match self.precedence {
+            Precedence::First => matches.next(),
+            // Ties go to the rule listed first
+            Precedence::Severity => matches.fold(None, |best: Option<&Rule>, rule| match best {
+                Some(best) if best.severity >= rule.severity => Some(best),
+                _ => Some(rule),
+            }),
+        }?;
+        Some(Class {
+            severity: rule.severity,
+            color: rule.color.unwrap_or(rule.severity.color()),
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Broken-down wall clock time in the local timezone (UTC where the
+/// platform offers no cheap way to look the offset up).
+#[derive(Clone, Copy, Debug)]
+pub struct WallTime {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millis: u32,
+}
+
+impl WallTime {
+    pub fn of(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        WallTime {
+            millis: since_epoch.subsec_millis(),
+            ..local(since_epoch.as_secs())
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::of(SystemTime::now())
+    }
+
+    /// `HH:MM:SS`
+    pub fn hms(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+
+    /// `HH:MM:SS.mmm`
+    pub fn hms_millis(&self) -> String {
+        format!("{}.{:03}", self.hms(), self.millis)
+    }
+}
+
+/// `time` in UTC as RFC 3339, e.g. `2024-03-09T17:05:42Z`.
+pub fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_date(secs / 86_400);
+    let WallTime {
+        hour,
+        minute,
+        second,
+        ..
+    } = utc(secs);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Hours from the epoch to `time` as the local clock reads it, so the count
+/// changes exactly when the clock's hour or date does, however long apart
+/// two times are. When the clock is set back, the repeated hour counts the
+/// same as the first time round.
+pub fn local_hours(time: SystemTime) -> i64 {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    (secs as i64 + utc_offset(secs)).div_euclid(3600)
+}
+
+/// Year, month and day of the day `days` after 1970-01-01, in the
+/// proleptic Gregorian calendar.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Count from 0000-03-01, so leap days fall at the end of a year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // March is 0
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(unix)]
+fn local(secs: u64) -> WallTime {
+    let t = secs as libc::time_t;
+    // SAFETY: localtime_r only writes into the zeroed `tm` we hand it.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        return utc(secs);
+    }
+    WallTime {
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+        second: tm.tm_sec as u32,
+        millis: 0,
+    }
+}
+
+#[cfg(not(unix))]
+fn local(secs: u64) -> WallTime {
+    utc(secs)
+}
+
+/// Seconds the local clock is ahead of UTC at `secs`.
+#[cfg(unix)]
+fn utc_offset(secs: u64) -> i64 {
+    let t = secs as libc::time_t;
+    // SAFETY: localtime_r only writes into the zeroed `tm` we hand it.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+#[cfg(not(unix))]
+fn utc_offset(_secs: u64) -> i64 {
+    0
+}
+
+fn utc(secs: u64) -> WallTime {
+    let day_secs = secs % 86_400;
+    WallTime {
+        hour: (day_secs / 3600) as u32,
+        minute: (day_secs / 60 % 60) as u32,
+        second: (day_secs % 60) as u32,
+        millis: 0,
+    }
+}
@@ -0,0 +1,327 @@
+use crate::checksum::Algorithm;
+use crate::classify::Severity;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// Share of the height the main pane may be given.
+pub const SPLIT_RANGE: RangeInclusive<u16> = 10..=90;
+
+/// Settings read from `config.toml`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Highlight rules for every pane that has no rules of its own.
+    pub highlight: Vec<HighlightConfig>,
+    pub panes: PanesConfig,
+    /// Rules deciding which lines are errors or warnings, replacing the
+    /// built-in ones when set.
+    pub classify: Option<Vec<ClassifyConfig>>,
+    /// Which rule wins when several classify rules match a line.
+    pub classify_precedence: Precedence,
+    /// Let the built-in keywords match inside other words.
+    pub loose_keywords: bool,
+    pub send: SendConfig,
+    /// Baud rates switched to with Alt+digit, keyed by the digit.
+    pub baud_presets: BTreeMap<String, u32>,
+    /// Two percentages of the height for the main pane, flipped between
+    /// with `=`.
+    pub split_presets: Option<[u16; 2]>,
+    /// Keys for palette commands, keyed by command id, replacing their
+    /// default keys.
+    pub keys: BTreeMap<String, String>,
+    pub titles: TitlesConfig,
+    pub selection: SelectionConfig,
+    pub strip: StripConfig,
+    /// Checksum embedded in received lines, flagged as an error when wrong.
+    pub checksum: Option<ChecksumConfig>,
+    /// Extra panes for received lines matching a pattern, checked in order.
+    pub route: Vec<RouteConfig>,
+    /// Tags and colors of the ports read together with `--extra-port`.
+    pub source: Vec<SourceConfig>,
+    /// How the routed panes share the top of the screen with the main pane.
+    pub route_layout: RouteLayout,
+    /// Where errors and warnings are shown, unless `--error-layout` says.
+    pub error_layout: ErrorLayout,
+    /// Patterns that ring the terminal bell when a received line matches.
+    pub bell: Option<BellConfig>,
+    /// Patterns marking that the device got where it should, such as the end
+    /// of its boot.
+    pub success: Option<SuccessConfig>,
+    /// Reading of `key=value` lines, turning the mode on when present.
+    pub structured: Option<StructuredConfig>,
+    /// Reading of `<N>` level prefixes, turning it on when present.
+    pub numeric_levels: Option<NumericLevelsConfig>,
+}
+
+/// Received lines that ring the terminal bell, for an audible cue on
+/// unattended runs.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BellConfig {
+    /// Regexes, any of which rings the bell.
+    pub patterns: Vec<String>,
+    /// Milliseconds after ringing during which more matches stay silent.
+    pub quiet_ms: Option<u64>,
+}
+
+/// Keys of `key=value` lines with a meaning of their own, and how the
+/// lines are shown.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StructuredConfig {
+    /// Key whose value is the line's level, `level` by default.
+    pub level_key: Option<String>,
+    /// Key whose value is the message, `msg` by default.
+    pub message_key: Option<String>,
+    /// Show the pairs in aligned columns, level and message first.
+    pub columns: bool,
+}
+
+/// Received lines that mean success, flashing the main pane's border green
+/// and optionally doing more.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SuccessConfig {
+    /// Regexes, any of which marks success.
+    pub patterns: Vec<String>,
+    /// Also ring the terminal bell.
+    pub bell: bool,
+    /// Pause the display at the line, as `p` does.
+    pub pause: bool,
+    /// Quit, with exit status 0.
+    pub exit: bool,
+}
+
+/// Which syslog-style numeric levels count as errors, warnings and debug
+/// output, and whether lines are shown with them.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct NumericLevelsConfig {
+    /// Highest severity that is an error, 3 by default.
+    pub error: Option<u8>,
+    /// Highest severity that is a warning, 4 by default.
+    pub warning: Option<u8>,
+    /// Lowest severity that is debug output, 7 by default.
+    pub debug: Option<u8>,
+    /// Show lines without their `<N>` prefix; the log keeps it.
+    pub strip: bool,
+}
+
+/// A named pane taking the received lines that match `pattern` (a regex)
+/// and are not errors or warnings.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// How the lines of one port are tagged when several are read at once.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SourceConfig {
+    /// Port name, such as `/dev/ttyUSB1` or `COM4`.
+    pub port: String,
+    /// Tag shown before its lines, a letter by default.
+    pub tag: Option<String>,
+    /// Color of the tag.
+    pub color: Option<String>,
+}
+
+/// Arrangement of the main pane and the routed panes.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteLayout {
+    /// Stacked above each other.
+    #[default]
+    Rows,
+    /// Side by side.
+    Columns,
+}
+
+/// Where classified lines go.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorLayout {
+    /// One pane under the main one, coloured by severity.
+    #[default]
+    Combined,
+    /// An errors pane and a warnings pane side by side.
+    Split,
+    /// Left in the main pane, as with `--no-classify`.
+    Inline,
+}
+
+/// Pane titles to show instead of the built-in ones.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TitlesConfig {
+    pub main: Option<String>,
+    pub errors: Option<String>,
+    pub warnings: Option<String>,
+}
+
+/// Colors of the lines selected in visual mode, replacing the line's own.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectionConfig {
+    /// Text color, `white` by default.
+    pub fg: Option<String>,
+    /// Background color, `blue` by default.
+    pub bg: Option<String>,
+}
+
+/// Where a line's checksum and the data it covers are, and how it is
+/// computed.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksumConfig {
+    /// Regex with `payload` and `checksum` groups, or those two in order.
+    pub pattern: String,
+    pub algorithm: Algorithm,
+}
+
+/// Tag to remove from the start of received lines before they are shown;
+/// the log keeps whole lines.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StripConfig {
+    /// Removed when a line starts with exactly this.
+    pub prefix: Option<String>,
+    /// Regex for the prefix, matched at the start of the line.
+    pub pattern: Option<String>,
+}
+
+/// How files are sent, unless overridden by the `--send-*` options.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SendConfig {
+    /// Pause after each line, in milliseconds.
+    pub line_delay_ms: Option<u64>,
+    /// Pause after each byte, in milliseconds.
+    pub byte_delay_ms: Option<u64>,
+    /// Bytes per second.
+    pub rate: Option<u32>,
+    /// Wait for a received line containing this after each line.
+    pub prompt: Option<String>,
+    /// Use XON/XOFF software flow control on the port.
+    pub xon_xoff: bool,
+}
+
+/// Per-pane overrides, keyed by pane.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct PanesConfig {
+    pub main: PaneConfig,
+    pub errors: PaneConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaneConfig {
+    /// Replaces the top-level `highlight` rules for this pane when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<Vec<HighlightConfig>>,
+}
+
+/// Colour every line matching `pattern` (a regex) with `color`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightConfig {
+    pub pattern: String,
+    pub color: String,
+}
+
+/// Put lines matching `pattern` (a regex) in the error/warning pane.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ClassifyConfig {
+    pub pattern: String,
+    pub severity: Severity,
+    /// Colour to use instead of the severity's own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// Which classify rule wins when several match.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Precedence {
+    /// The first matching rule in the order they are listed.
+    #[default]
+    First,
+    /// The matching rule with the highest severity, then the first listed.
+    Severity,
+}
+
+impl Config {
+    /// Load `path`, or the default config file if `path` is `None`.
+    ///
+    /// A missing default config file is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Baud presets as `(digit, rate)` pairs.
+    pub fn baud_presets(&self) -> Result<Vec<(char, u32)>, String> {
+        self.baud_presets
+            .iter()
+            .map(|(key, &rate)| {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(digit), None) if digit.is_ascii_digit() => Ok((digit, rate)),
+                    _ => Err(format!(
+                        "Baud preset keys must be a single digit, not {:?}",
+                        key
+                    )),
+                }
+            })
+            .collect()
+    }
+
+    /// Split presets, 80/20 and 50/50 unless set.
+    pub fn split_presets(&self) -> Result<[u16; 2], String> {
+        let presets = self.split_presets.unwrap_or([80, 50]);
+        match presets
+            .iter()
+            .find(|percent| !SPLIT_RANGE.contains(percent))
+        {
+            Some(percent) => Err(format!(
+                "Split presets must be between {} and {} percent, not {}",
+                SPLIT_RANGE.start(),
+                SPLIT_RANGE.end(),
+                percent
+            )),
+            None => Ok(presets),
+        }
+    }
+}
+
+/// Directory holding aserial's config files.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join("aserial"))
+}
+
+fn default_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
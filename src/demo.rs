@@ -0,0 +1,51 @@
+use crate::line::{Message, MessageSender, Payload, ReaderControl, PAUSE_POLL};
+use std::thread;
+use std::time::Duration;
+
+/// Spawn a thread that sends made-up device output through `tx`, `rate`
+/// lines per second, for trying the UI without hardware.
+///
+/// The stream is the same on every run: mostly sensor readings, with a
+/// warning every 7th line and an error every 23rd.
+pub fn spawn(rate: u32, control: ReaderControl, tx: MessageSender) {
+    let interval = Duration::from_secs(1) / rate.max(1);
+    thread::spawn(move || {
+        let mut number: u64 = 0;
+        loop {
+            if control.is_paused() {
+                thread::sleep(PAUSE_POLL);
+                continue;
+            }
+            number += 1;
+            if tx.send(Message::Line(Payload::rx(line(number)))).is_err() {
+                return;
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+/// Line `number` of the demo stream.
+pub fn line(number: u64) -> String {
+    // Uptime as if lines arrived every 10 ms
+    let uptime = format!("[{:>5}.{:03}]", number / 100, number % 100 * 10);
+    if number.is_multiple_of(23) {
+        format!(
+            "{} ERROR: i2c: no ACK from 0x{:02x}",
+            uptime,
+            0x40 + number % 8
+        )
+    } else if number.is_multiple_of(7) {
+        format!("{} WARN: battery at {}%", uptime, 100 - number % 90)
+    } else {
+        let temp = 200 + (number * 37) % 50;
+        format!(
+            "{} sensor: temp={}.{}C humidity={}% sample={}",
+            uptime,
+            temp / 10,
+            temp % 10,
+            40 + (number * 13) % 20,
+            number
+        )
+    }
+}
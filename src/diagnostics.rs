@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Rows the debug pane takes up, borders included.
+pub const DEBUG_HEIGHT: u16 = 6;
+
+/// Read sizes kept for the debug pane.
+const RECENT_READS: usize = 12;
+
+/// What the reader has been doing, kept with `--debug` to tell a device
+/// misbehaving from the monitor doing so.
+///
+/// The reader updates it as it goes and the UI shows it every frame.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    reads: AtomicU64,
+    bytes: AtomicU64,
+    largest_read: AtomicUsize,
+    timeouts: AtomicU64,
+    lines: AtomicU64,
+    forced_splits: AtomicU64, // Lines passed on at `MAX_LINE_BYTES` without a newline
+    disconnects: AtomicU64,
+    reconnect_attempts: AtomicU64,
+    peak_queued: AtomicUsize,
+    recent: Mutex<VecDeque<usize>>, // Sizes of the last reads, oldest first
+    last_error: Mutex<Option<String>>, // Why the port last failed
+}
+
+impl Diagnostics {
+    pub fn read(&self, size: usize) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        self.largest_read.fetch_max(size, Ordering::Relaxed);
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_READS {
+            recent.pop_front();
+        }
+        recent.push_back(size);
+    }
+
+    // The async reader waits for data without timing out
+    #[cfg(not(feature = "async"))]
+    pub fn timeout(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn line(&self, forced: bool) {
+        self.lines.fetch_add(1, Ordering::Relaxed);
+        if forced {
+            self.forced_splits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn disconnected(&self, error: &str) {
+        self.disconnects.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.to_string());
+    }
+
+    pub fn reconnecting(&self) {
+        self.reconnect_attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Note how many messages wait in the channel, to keep the peak.
+    pub fn queued(&self, queued: usize) {
+        self.peak_queued.fetch_max(queued, Ordering::Relaxed);
+    }
+
+    /// Rows of the debug pane, given the messages queued now.
+    pub fn rows(&self, queued: usize) -> Vec<String> {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        let recent: Vec<String> = recent.iter().map(|size| size.to_string()).collect();
+        vec![
+            format!(
+                "reads {}  bytes {}  largest read {} B  recent reads (B): {}",
+                get(&self.reads),
+                get(&self.bytes),
+                self.largest_read.load(Ordering::Relaxed),
+                recent.join(" ")
+            ),
+            format!(
+                "timeouts {}  lines {}  split at the length limit {}",
+                get(&self.timeouts),
+                get(&self.lines),
+                get(&self.forced_splits)
+            ),
+            format!(
+                "disconnects {}  reconnect attempts {}  last error: {}",
+                get(&self.disconnects),
+                get(&self.reconnect_attempts),
+                self.last_error
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .as_deref()
+                    .unwrap_or("none")
+            ),
+            format!(
+                "queued messages {}  peak {}",
+                queued,
+                self.peak_queued.load(Ordering::Relaxed)
+            ),
+        ]
+    }
+}
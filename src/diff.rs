@@ -0,0 +1,110 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// One line of a line-based diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Shortest edit script turning `old` into `new` (Myers' algorithm).
+pub fn diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Change<'a>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize;
+
+    // Furthest x reached on each diagonal k = x - y, indexed by k + offset.
+    // Before each step d the diagonals -d..=d are saved for backtracking.
+    let mut v = vec![0isize; 2 * max + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=max as isize {
+        let lo = (offset - d) as usize;
+        trace.push(v[lo..lo + 2 * d as usize + 1].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut changes = Vec::with_capacity(max);
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        if d == 0 {
+            while x > 0 && y > 0 {
+                changes.push(Change::Same(old[x as usize - 1]));
+                x -= 1;
+                y -= 1;
+            }
+            break;
+        }
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            changes.push(Change::Same(old[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if x == prev_x {
+            changes.push(Change::Added(new[y as usize - 1]));
+        } else {
+            changes.push(Change::Removed(old[x as usize - 1]));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    changes.reverse();
+    changes
+}
+
+/// Two capture files loaded for comparison.
+pub struct Captures {
+    pub old_name: String,
+    pub new_name: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl Captures {
+    pub fn read(old: &Path, new: &Path) -> io::Result<Self> {
+        let read =
+            |path: &Path| fs::read(path).map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+        Ok(Captures {
+            old_name: old.display().to_string(),
+            new_name: new.display().to_string(),
+            old: read(old)?,
+            new: read(new)?,
+        })
+    }
+
+    /// Line-based diff of the two captures.
+    pub fn diff(&self) -> Vec<Change<'_>> {
+        let old: Vec<&str> = self.old.lines().collect();
+        let new: Vec<&str> = self.new.lines().collect();
+        diff(&old, &new)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::clock::WallTime;
+use crate::highlight;
+use crate::pane::Pane;
+use crate::text::{self, NulDisplay};
+use ratatui::style::Color;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Write `panes`, as `(title, pane)` pairs, to `path` as a self-contained
+/// HTML document in the colours they have on screen, with NULs shown as
+/// `nul` says.
+pub fn write_html(
+    path: &Path,
+    panes: &[(&str, &Pane)],
+    timestamps: bool,
+    nul: NulDisplay,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>aserial capture</title>")?;
+    writeln!(out, "</head>")?;
+    // Inline styles only, so the file renders the same wherever it is opened
+    writeln!(
+        out,
+        "<body style=\"background:#1e1e1e;color:#d0d0d0;font-family:monospace\">"
+    )?;
+    for (title, pane) in panes {
+        writeln!(out, "<h2 style=\"font-size:1em\">{}</h2>", escape(title))?;
+        write!(out, "<pre style=\"margin:0 0 2em 0\">")?;
+        for line in &pane.lines {
+            if timestamps {
+                write!(
+                    out,
+                    "<span style=\"color:#808080\">{} </span>",
+                    WallTime::of(line.time).hms()
+                )?;
+            }
+            let color = highlight::color_for(&pane.highlights, &line.text).unwrap_or(line.color);
+            writeln!(
+                out,
+                "<span style=\"color:{}\">{}</span>",
+                css_color(color),
+                escape(&text::show_nul(&text::strip_ansi(&line.text), nul))
+            )?;
+        }
+        writeln!(out, "</pre>")?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    out.flush()
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// CSS colour for a terminal colour.
+fn css_color(color: Color) -> String {
+    let (r, g, b) = rgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// RGB value of a terminal colour in the xterm palette.
+fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) => indexed_rgb(i),
+        Color::Black => (0x00, 0x00, 0x00),
+        Color::Red => (0xcd, 0x00, 0x00),
+        Color::Green => (0x00, 0xcd, 0x00),
+        Color::Yellow => (0xcd, 0xcd, 0x00),
+        Color::Blue => (0x00, 0x00, 0xee),
+        Color::Magenta => (0xcd, 0x00, 0xcd),
+        Color::Cyan => (0x00, 0xcd, 0xcd),
+        Color::Gray => (0xe5, 0xe5, 0xe5),
+        Color::DarkGray => (0x7f, 0x7f, 0x7f),
+        Color::LightRed => (0xff, 0x00, 0x00),
+        Color::LightGreen => (0x00, 0xff, 0x00),
+        Color::LightYellow => (0xff, 0xff, 0x00),
+        Color::LightBlue => (0x5c, 0x5c, 0xff),
+        Color::LightMagenta => (0xff, 0x00, 0xff),
+        Color::LightCyan => (0x00, 0xff, 0xff),
+        Color::White | Color::Reset => (0xff, 0xff, 0xff),
+    }
+}
+
+/// RGB value of a 256-colour palette index.
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    const BASIC: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    match i {
+        0..=15 => rgb(BASIC[i as usize]),
+        // 6x6x6 colour cube
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = i - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        // Greyscale ramp
+        _ => {
+            let grey = 8 + (i - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
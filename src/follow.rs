@@ -0,0 +1,100 @@
+use crate::line::{Message, MessageSender, Payload, ReaderControl, PAUSE_POLL};
+use crate::text;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How often to look for new data once the end of the file is reached.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Spawn a thread that follows `path` like `tail -f` and sends every newly
+/// appended line through `tx`.
+///
+/// A truncated file is read again from the start; a rotated file (a new file
+/// at the same path) is reopened.
+pub fn spawn(path: PathBuf, control: ReaderControl, tx: MessageSender) {
+    thread::spawn(move || {
+        let mut reader: Option<BufReader<File>> = None;
+        let mut identity = None;
+        let mut position = 0;
+        let mut partial_line = Vec::new();
+        loop {
+            if control.is_paused() {
+                thread::sleep(PAUSE_POLL);
+                continue;
+            }
+            let Some(file) = reader.as_mut() else {
+                // Wait for the file to (re)appear, then start at its end
+                match open_at_end(&path) {
+                    Ok((file, end)) => {
+                        identity = file_identity(&path);
+                        position = end;
+                        reader = Some(BufReader::new(file));
+                    }
+                    Err(_) => thread::sleep(POLL_INTERVAL),
+                }
+                continue;
+            };
+
+            match file.read_until(b'\n', &mut partial_line) {
+                Ok(0) => {
+                    thread::sleep(POLL_INTERVAL);
+                    let rotated = file_identity(&path) != identity;
+                    let truncated = fs::metadata(&path).is_ok_and(|m| m.len() < position);
+                    if rotated {
+                        // Read the new file from its start so nothing is missed
+                        reader = File::open(&path).ok().map(BufReader::new);
+                        identity = file_identity(&path);
+                        position = 0;
+                        partial_line.clear();
+                    } else if truncated {
+                        if file.seek(SeekFrom::Start(0)).is_err() {
+                            reader = None;
+                        }
+                        position = 0;
+                        partial_line.clear();
+                    }
+                }
+                Ok(bytes_read) => {
+                    position += bytes_read as u64;
+                    // Keep accumulating until the writer finishes the line
+                    if partial_line.ends_with(b"\n") {
+                        let data = text::decode(&partial_line, control.decoding());
+                        let line = data.trim_end_matches(['\r', '\n']);
+                        if tx
+                            .send(Message::Line(Payload::rx(line.to_string())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                        partial_line.clear();
+                    }
+                }
+                Err(_) => {
+                    reader = None;
+                    partial_line.clear();
+                }
+            }
+        }
+    });
+}
+
+fn open_at_end(path: &Path) -> io::Result<(File, u64)> {
+    let mut file = File::open(path)?;
+    let end = file.seek(SeekFrom::End(0))?;
+    Ok((file, end))
+}
+
+/// Something that changes when a different file appears at `path`.
+#[cfg(unix)]
+fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).ok().and_then(|m| m.created().ok())
+}
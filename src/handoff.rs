@@ -0,0 +1,133 @@
+use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+/// Settings of the open port, as other serial terminals take them.
+pub struct PortSettings {
+    pub name: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: u8,
+    pub flow_control: FlowControl,
+}
+
+impl PortSettings {
+    /// Read the settings back from the port, so they match what is in use.
+    pub fn of(port: &dyn SerialPort) -> serialport::Result<Self> {
+        Ok(PortSettings {
+            name: port.name().unwrap_or_default(),
+            baud_rate: port.baud_rate()?,
+            data_bits: match port.data_bits()? {
+                DataBits::Five => 5,
+                DataBits::Six => 6,
+                DataBits::Seven => 7,
+                DataBits::Eight => 8,
+            },
+            parity: port.parity()?,
+            stop_bits: match port.stop_bits()? {
+                StopBits::One => 1,
+                StopBits::Two => 2,
+            },
+            flow_control: port.flow_control()?,
+        })
+    }
+
+    /// Framing in the usual short form, e.g. `8N1`.
+    pub fn framing(&self) -> String {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+        };
+        format!("{}{}{}", self.data_bits, parity, self.stop_bits)
+    }
+
+    /// Command lines opening the same port the same way in screen, minicom
+    /// and picocom.
+    pub fn commands(&self) -> Vec<String> {
+        vec![self.screen(), self.minicom(), self.picocom()]
+    }
+
+    fn screen(&self) -> String {
+        let parity = match self.parity {
+            Parity::None => "-parenb",
+            Parity::Odd => "parenb,parodd",
+            Parity::Even => "parenb,-parodd",
+        };
+        let stop_bits = if self.stop_bits == 2 {
+            "cstopb"
+        } else {
+            "-cstopb"
+        };
+        let flow = match self.flow_control {
+            FlowControl::None => "-ixon,-ixoff",
+            FlowControl::Software => "ixon,ixoff",
+            FlowControl::Hardware => "-ixon,-ixoff,crtscts",
+        };
+        format!(
+            "screen {} {},cs{},{},{},{}",
+            quote(&self.name),
+            self.baud_rate,
+            self.data_bits,
+            parity,
+            stop_bits,
+            flow
+        )
+    }
+
+    /// minicom only takes the port and rate on the command line, and turns
+    /// hardware flow control on by default, so the rest is left as a note.
+    fn minicom(&self) -> String {
+        let mut command = format!("minicom -D {} -b {}", quote(&self.name), self.baud_rate);
+        let mut setup = Vec::new();
+        if self.framing() != "8N1" {
+            setup.push(self.framing());
+        }
+        if self.flow_control != FlowControl::Hardware {
+            setup.push("hardware flow control off".to_string());
+        }
+        if self.flow_control == FlowControl::Software {
+            setup.push("software flow control on".to_string());
+        }
+        if !setup.is_empty() {
+            command.push_str(&format!(
+                "  # then set {} in Ctrl-A O, Serial port setup",
+                setup.join(", ")
+            ));
+        }
+        command
+    }
+
+    fn picocom(&self) -> String {
+        let parity = match self.parity {
+            Parity::None => 'n',
+            Parity::Odd => 'o',
+            Parity::Even => 'e',
+        };
+        let flow = match self.flow_control {
+            FlowControl::None => 'n',
+            FlowControl::Software => 'x',
+            FlowControl::Hardware => 'h',
+        };
+        format!(
+            "picocom -b {} -d {} -p {} -s {} -f {} {}",
+            self.baud_rate,
+            self.data_bits,
+            parity,
+            self.stop_bits,
+            flow,
+            quote(&self.name)
+        )
+    }
+}
+
+/// `text` quoted for a POSIX shell if it needs to be.
+fn quote(text: &str) -> String {
+    if text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-:".contains(c))
+    {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::{HighlightConfig, SelectionConfig};
+use crate::pane::PaneLine;
+use ratatui::style::{Color, Modifier, Style};
+use regex::Regex;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Words too common in commands to say which response belongs to them.
+const COMMAND_VERBS: &[&str] = &["get", "set", "read", "write", "show", "list", "query"];
+
+/// A compiled highlight rule.
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    pub regex: Regex,
+    pub color: Color,
+}
+
+impl Highlight {
+    pub fn compile(rule: &HighlightConfig) -> Result<Self, String> {
+        let regex = Regex::new(&rule.pattern)
+            .map_err(|e| format!("Invalid highlight pattern {:?}: {}", rule.pattern, e))?;
+        let color = Color::from_str(&rule.color)
+            .map_err(|_| format!("Invalid highlight color {:?}", rule.color))?;
+        Ok(Highlight { regex, color })
+    }
+
+    pub fn compile_all(rules: &[HighlightConfig]) -> Result<Vec<Self>, String> {
+        rules.iter().map(Highlight::compile).collect()
+    }
+}
+
+/// How the lines selected in visual mode are drawn. Both colors replace the
+/// line's own, so a selected error or warning line stands out as much as any
+/// other.
+#[derive(Clone, Copy, Debug)]
+pub struct SelectionStyle {
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl SelectionStyle {
+    pub fn new() -> Self {
+        SelectionStyle {
+            fg: Color::White,
+            bg: Color::Blue,
+        }
+    }
+
+    pub fn from_config(config: &SelectionConfig) -> Result<Self, String> {
+        let parse = |color: &Option<String>, default: Color| match color {
+            Some(name) => {
+                Color::from_str(name).map_err(|_| format!("Invalid selection color {:?}", name))
+            }
+            None => Ok(default),
+        };
+        let defaults = SelectionStyle::new();
+        let style = SelectionStyle {
+            fg: parse(&config.fg, defaults.fg)?,
+            bg: parse(&config.bg, defaults.bg)?,
+        };
+        if style.fg == style.bg {
+            return Err("Invalid [selection]: fg and bg are the same color".to_string());
+        }
+        Ok(style)
+    }
+
+    /// Style of a selected line; the line under the cursor is also bold.
+    pub fn style(&self, cursor: bool) -> Style {
+        let style = Style::default().fg(self.fg).bg(self.bg);
+        if cursor {
+            style.add_modifier(Modifier::BOLD)
+        } else {
+            style
+        }
+    }
+}
+
+/// The words of the last command sent, marking the lines received after it
+/// that mention them: sending `get_temp` marks the lines containing `temp`.
+#[derive(Clone, Debug)]
+pub struct ResponseMatch {
+    words: Vec<String>, // Lowercase, at least three characters each
+    since: SystemTime,  // Lines after this answer the command
+}
+
+impl ResponseMatch {
+    pub fn new(command: &str) -> Self {
+        let words = command
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() >= 3)
+            .map(str::to_lowercase)
+            .filter(|word| !COMMAND_VERBS.contains(&word.as_str()))
+            .collect();
+        ResponseMatch {
+            words,
+            since: SystemTime::now(),
+        }
+    }
+
+    /// Start from now, so the command's own line and echo are not marked.
+    pub fn restart(&mut self) {
+        self.since = SystemTime::now();
+    }
+
+    pub fn is_match(&self, line: &PaneLine) -> bool {
+        if self.words.is_empty() || line.time <= self.since {
+            return false;
+        }
+        let text = line.text.to_lowercase();
+        self.words.iter().any(|word| text.contains(word.as_str()))
+    }
+}
+
+/// Colour of the first rule matching `line`, if any.
+pub fn color_for(rules: &[Highlight], line: &str) -> Option<Color> {
+    rules
+        .iter()
+        .find(|rule| rule.regex.is_match(line))
+        .map(|rule| rule.color)
+}
@@ -0,0 +1,102 @@
+use crate::palette::{Action, Command, COMMANDS};
+use crossterm::event::KeyCode;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Keys bound to palette commands: the defaults listed in `COMMANDS`, with
+/// the bindings of the `[keys]` config section applied on top.
+#[derive(Debug, Default)]
+pub struct Keymap {
+    bound: HashMap<KeyCode, Action>,       // Keys the config binds
+    released: HashSet<KeyCode>,            // Default keys of commands the config moved
+    labels: HashMap<&'static str, String>, // Key shown for a command, by name, where not the default
+}
+
+impl Keymap {
+    /// Apply `keys`, which maps command ids such as `quit` to a key, or to
+    /// `""` to leave the command without one.
+    pub fn from_config(keys: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut keymap = Keymap::default();
+        let mut owners: HashMap<KeyCode, &str> = HashMap::new();
+        for (id, text) in keys {
+            let command = COMMANDS
+                .iter()
+                .find(|command| command.id() == *id)
+                .ok_or_else(|| format!("Unknown command {:?} in [keys]", id))?;
+            if let Action::Key(code) = command.action {
+                keymap.released.insert(code);
+            }
+            keymap.labels.insert(command.name, text.clone());
+            if text.is_empty() {
+                continue;
+            }
+            let code = parse_key(text)
+                .ok_or_else(|| format!("Unknown key {:?} for {} in [keys]", text, id))?;
+            if let Some(other) = owners.insert(code, id) {
+                return Err(format!(
+                    "Key {:?} is bound to both {} and {} in [keys]",
+                    text, other, id
+                ));
+            }
+            keymap.bound.insert(code, command.action);
+        }
+        // A command whose default key was taken is left without one
+        for command in COMMANDS {
+            if let Action::Key(code) = command.action {
+                if keymap.bound.contains_key(&code) && !keymap.labels.contains_key(command.name) {
+                    keymap.labels.insert(command.name, String::new());
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// What pressing `code` does, or `None` if the key was moved elsewhere.
+    pub fn resolve(&self, code: KeyCode) -> Option<Action> {
+        match self.bound.get(&code) {
+            Some(&action) => Some(action),
+            None if self.released.contains(&code) => None,
+            None => Some(Action::Key(code)),
+        }
+    }
+
+    /// The key that runs `command`, for the palette.
+    pub fn key_label<'a>(&'a self, command: &'a Command) -> &'a str {
+        self.labels
+            .get(command.name)
+            .map_or(command.key, String::as_str)
+    }
+}
+
+/// A key as written in the config: a single character such as `q` or `?`,
+/// or a name such as `Tab`, `PgDn`, `Space` or `F5`, ignoring case.
+pub fn parse_key(text: &str) -> Option<KeyCode> {
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match text.to_ascii_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        name => {
+            let number = name.strip_prefix('f')?.parse().ok()?;
+            if !(1..=12).contains(&number) {
+                return None;
+            }
+            KeyCode::F(number)
+        }
+    };
+    Some(code)
+}
@@ -0,0 +1,380 @@
+use crate::diagnostics::Diagnostics;
+use crate::port::PortSource;
+use crate::text::{self, Decoding};
+use regex::Regex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Which side of the link a line came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the device.
+    Rx,
+    /// Sent by us, or the device echoing something we sent.
+    Tx,
+}
+
+/// A line of text travelling between the reader thread and the UI.
+#[derive(Clone, Debug)]
+pub struct Payload {
+    pub text: String,
+    pub direction: Direction,
+    pub raw: Option<Vec<u8>>, // Bytes as read from the device, line ending included
+    pub source: Option<usize>, // Port it came from, when several are read
+}
+
+impl Payload {
+    pub fn rx(text: String) -> Self {
+        Payload {
+            text,
+            direction: Direction::Rx,
+            raw: None,
+            source: None,
+        }
+    }
+
+    /// A line read from the device as `raw`, from port `source` when
+    /// several are read.
+    pub fn received(text: String, raw: Vec<u8>, source: Option<usize>) -> Self {
+        Payload {
+            raw: Some(raw),
+            source,
+            ..Payload::rx(text)
+        }
+    }
+
+    pub fn tx(text: String) -> Self {
+        Payload {
+            text,
+            direction: Direction::Tx,
+            raw: None,
+            source: None,
+        }
+    }
+}
+
+/// What the reader sends to the UI.
+pub enum Message {
+    Line(Payload),
+    /// The line still being received was redrawn with a carriage return;
+    /// carries what it shows now.
+    Update(Payload),
+    /// The port failed and the reader is about to try reopening it.
+    Disconnected(String),
+    /// About to make reconnection attempt `attempt` of `max` (0 = no limit).
+    Reconnecting {
+        attempt: u32,
+        max: u32,
+    },
+    /// The port was reopened; carries a fresh handle for sending.
+    Reconnected(Box<dyn serialport::SerialPort>),
+    /// All reconnection attempts failed.
+    GaveUp,
+    /// The device printed its prompt, as a line or the start of one.
+    Prompt,
+    /// A problem the reader hit without losing the port.
+    Error(String),
+    /// The source has nothing more to send, e.g. a replay reached its end.
+    Ended,
+}
+
+/// Longest line kept before it is passed on unfinished, so a device that
+/// never sends a newline cannot grow it without bound.
+pub const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// Splits received bytes into lines, keeping raw bytes until a line is
+/// complete so characters split across reads decode correctly.
+pub struct LineSplitter {
+    partial: Vec<u8>,
+    control: ReaderControl,
+    reported: usize,       // End of the part of `partial` last sent as an update
+    prompted: bool,        // The line being received has matched the prompt
+    source: Option<usize>, // Port the lines come from, when several are read
+}
+
+impl LineSplitter {
+    pub fn new(control: ReaderControl) -> Self {
+        LineSplitter {
+            partial: Vec::new(),
+            control,
+            reported: 0,
+            prompted: false,
+            source: None,
+        }
+    }
+
+    /// Mark the lines as coming from `source`. Only the port lines are sent
+    /// to is watched for the prompt.
+    pub fn with_source(self, source: Option<&PortSource>) -> Self {
+        let Some(source) = source else {
+            return self;
+        };
+        LineSplitter {
+            control: match source.index {
+                0 => self.control,
+                _ => self.control.without_prompt(),
+            },
+            source: Some(source.index),
+            ..self
+        }
+    }
+
+    /// Take in freshly read bytes, returning the complete lines and any
+    /// carriage-return update to the line still being received.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Message> {
+        let decoding = self.control.decoding();
+        let mut messages = Vec::new();
+        for chunk in bytes.split_inclusive(|&b| b == b'\n') {
+            self.partial.extend_from_slice(chunk);
+            if self.partial.ends_with(b"\n") {
+                // A complete line, without its terminator
+                let data = text::decode(&self.partial, decoding);
+                let line = text::overwrite(data.trim_end_matches(['\r', '\n']));
+                messages.push(Message::Line(Payload::received(
+                    line.to_string(),
+                    self.partial.clone(),
+                    self.source,
+                )));
+                if self.is_new_prompt(line) {
+                    self.prompted = true;
+                    messages.push(Message::Prompt);
+                }
+                self.partial.clear();
+                self.reported = 0;
+                self.prompted = false;
+                if let Some(diagnostics) = self.control.diagnostics() {
+                    diagnostics.line(false);
+                }
+            } else if self.partial.len() >= MAX_LINE_BYTES {
+                let data = text::decode(&self.partial, decoding);
+                messages.push(Message::Line(Payload::received(
+                    data.into_owned(),
+                    self.partial.clone(),
+                    self.source,
+                )));
+                self.partial.clear();
+                self.reported = 0;
+                self.prompted = false;
+                if let Some(diagnostics) = self.control.diagnostics() {
+                    diagnostics.line(true);
+                }
+            }
+        }
+
+        // A trailing `\r` may yet turn out to be half of `\r\n`
+        let settled = self.partial.len() - usize::from(self.partial.ends_with(b"\r"));
+        let shown_end = if self.control.shows_partial() {
+            (settled > 0).then_some(settled)
+        } else {
+            self.partial[..settled].iter().rposition(|&b| b == b'\r')
+        };
+        if let Some(end) = shown_end {
+            if end != self.reported {
+                self.reported = end;
+                let data = text::decode(&self.partial[..end], decoding);
+                let shown = text::overwrite(&data);
+                if !shown.is_empty() {
+                    let mut update = Payload::rx(shown.to_string());
+                    update.source = self.source;
+                    messages.push(Message::Update(update));
+                }
+            }
+        }
+        // A shell prompt waits for input without ending its line
+        if settled > 0 && self.control.prompt().is_some() && !self.prompted {
+            let data = text::decode(&self.partial[..settled], decoding);
+            if self.is_new_prompt(text::overwrite(&data)) {
+                self.prompted = true;
+                messages.push(Message::Prompt);
+            }
+        }
+        messages
+    }
+
+    /// Whether `line`, complete or not, matches the prompt. A line that
+    /// matched while still being received is not reported again when it
+    /// ends, e.g. once the command typed after a prompt is echoed.
+    fn is_new_prompt(&self, line: &str) -> bool {
+        !self.prompted
+            && self
+                .control
+                .prompt()
+                .is_some_and(|prompt| prompt.is_match(line))
+    }
+}
+
+/// How long a paused reader waits before checking again.
+pub const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+/// Settings the UI can change while a reader runs: whether it consumes from
+/// the port at all, so data backs up in the driver and the device, how it
+/// decodes what it reads, whether it reports lines before they end, and
+/// which prompt it watches for. With `--debug` it also carries the counters
+/// the reader keeps for the debug pane.
+#[derive(Clone, Debug)]
+pub struct ReaderControl {
+    paused: Arc<AtomicBool>,
+    decoding: Arc<AtomicU8>,
+    partial: Arc<AtomicBool>, // Report lines still being received as they grow
+    prompt: Option<Regex>,    // Report lines, finished or not, matching this
+    diagnostics: Option<Arc<Diagnostics>>,
+    replay_speed: Option<Arc<AtomicU64>>, // Bits of the f64 multiplier, when replaying
+}
+
+impl ReaderControl {
+    pub fn new(decoding: Decoding) -> Self {
+        ReaderControl {
+            paused: Arc::default(),
+            decoding: Arc::new(AtomicU8::new(decoding as u8)),
+            partial: Arc::default(),
+            prompt: None,
+            diagnostics: None,
+            replay_speed: None,
+        }
+    }
+
+    /// Have the reader report the device's prompt.
+    pub fn with_prompt(self, prompt: Regex) -> Self {
+        ReaderControl {
+            prompt: Some(prompt),
+            ..self
+        }
+    }
+
+    /// The same control, not watching for the prompt.
+    pub fn without_prompt(self) -> Self {
+        ReaderControl {
+            prompt: None,
+            ..self
+        }
+    }
+
+    pub fn prompt(&self) -> Option<&Regex> {
+        self.prompt.as_ref()
+    }
+
+    /// Have the reader keep diagnostics.
+    pub fn with_diagnostics(self) -> Self {
+        ReaderControl {
+            diagnostics: Some(Arc::default()),
+            ..self
+        }
+    }
+
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        self.diagnostics.as_deref()
+    }
+
+    /// Have the reader play a recording back at `speed` times its pace.
+    pub fn with_replay_speed(self, speed: f64) -> Self {
+        ReaderControl {
+            replay_speed: Some(Arc::new(AtomicU64::new(speed.to_bits()))),
+            ..self
+        }
+    }
+
+    /// How many times faster than recorded a replay runs, if replaying.
+    pub fn replay_speed(&self) -> Option<f64> {
+        let speed = self.replay_speed.as_ref()?;
+        Some(f64::from_bits(speed.load(Ordering::Relaxed)))
+    }
+
+    pub fn set_replay_speed(&self, speed: f64) {
+        if let Some(shared) = &self.replay_speed {
+            shared.store(speed.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Send the line still being received as an update whenever more of it
+    /// arrives, not only when it is redrawn with a carriage return.
+    pub fn set_show_partial(&self, show: bool) {
+        self.partial.store(show, Ordering::Relaxed);
+    }
+
+    pub fn shows_partial(&self) -> bool {
+        self.partial.load(Ordering::Relaxed)
+    }
+
+    /// Applies from the next line completed.
+    pub fn set_decoding(&self, decoding: Decoding) {
+        self.decoding.store(decoding as u8, Ordering::Relaxed);
+    }
+
+    pub fn decoding(&self) -> Decoding {
+        Decoding::from_u8(self.decoding.load(Ordering::Relaxed))
+    }
+}
+
+/// Sending end of the channel feeding the UI.
+#[derive(Clone)]
+pub struct MessageSender {
+    #[cfg(not(feature = "async"))]
+    inner: std::sync::mpsc::Sender<Message>,
+    #[cfg(feature = "async")]
+    inner: tokio::sync::mpsc::UnboundedSender<Message>,
+    queued: Arc<AtomicUsize>, // Sent but not yet received
+}
+
+impl MessageSender {
+    /// Queue `message` for the UI, handing it back if the UI has gone away.
+    pub fn send(&self, message: Message) -> Result<(), Message> {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        self.inner.send(message).map_err(|e| {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            e.0
+        })
+    }
+
+    /// Messages sent but not yet received, i.e. how far the UI is behind.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+}
+
+/// Receiving end of the channel feeding the UI.
+pub struct MessageReceiver {
+    #[cfg(not(feature = "async"))]
+    inner: std::sync::mpsc::Receiver<Message>,
+    #[cfg(feature = "async")]
+    inner: tokio::sync::mpsc::UnboundedReceiver<Message>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl MessageReceiver {
+    /// Next message if one is waiting, without blocking.
+    pub fn try_recv(&mut self) -> Option<Message> {
+        let message = self.inner.try_recv().ok()?;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        Some(message)
+    }
+
+    /// Messages sent but not yet received, i.e. how far the UI is behind.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+}
+
+/// Channel for sending lines and connection events from the reader to the UI.
+pub fn channel() -> (MessageSender, MessageReceiver) {
+    #[cfg(not(feature = "async"))]
+    let (tx, inner) = std::sync::mpsc::channel();
+    #[cfg(feature = "async")]
+    let (tx, inner) = tokio::sync::mpsc::unbounded_channel();
+    let queued = Arc::new(AtomicUsize::new(0));
+    (
+        MessageSender {
+            inner: tx,
+            queued: queued.clone(),
+        },
+        MessageReceiver { inner, queued },
+    )
+}
@@ -0,0 +1,282 @@
+use crate::clock::{self, WallTime};
+use crate::text;
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+/// Starts every header and footer line; the version changes only if the
+/// format stops being compatible.
+pub const HEADER_PREFIX: &str = "# aserial-log v1";
+
+/// `key=value` fields of a header or footer line, in order.
+pub type Fields = Vec<(&'static str, String)>;
+
+/// Line terminator written after each log line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Newline {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl Newline {
+    /// `\r\n` on Windows, `\n` elsewhere.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Newline::Crlf
+        } else {
+            Newline::Lf
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::Crlf => "\r\n",
+        }
+    }
+}
+
+/// When a log moves on to its next numbered file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotate {
+    Size(u64), // Bytes a file may grow to
+    Hourly,
+    Daily,
+}
+
+/// `hourly`, `daily` or a size such as `10MB`, `512KB` or `1GB`.
+pub fn parse_rotate(text: &str) -> Result<Rotate, String> {
+    match text.to_ascii_lowercase().as_str() {
+        "hourly" => return Ok(Rotate::Hourly),
+        "daily" => return Ok(Rotate::Daily),
+        _ => {}
+    }
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected hourly, daily or a size like 10MB, not {:?}", text))?;
+    let unit = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" => 1024,
+        "MB" => 1024 * 1024,
+        "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("unknown unit {:?}, expected B, KB, MB or GB", unit)),
+    };
+    match number.checked_mul(unit) {
+        Some(0) => Err("a log file must be allowed at least one byte".to_string()),
+        Some(bytes) => Ok(Rotate::Size(bytes)),
+        None => Err(format!("{:?} is too large a size", text)),
+    }
+}
+
+/// How lines are written to a log file, independently of how the panes
+/// show them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogFormat {
+    pub strip_ansi: bool,       // Keep escape sequences out of the file
+    pub timestamps: bool,       // Prefix each line with the time it was written
+    pub line_numbers: bool,     // Prefix each line with its number in the file
+    pub newline: Newline,       // Ends every line, whatever the platform
+    pub rotate: Option<Rotate>, // Split the log over numbered files
+}
+
+/// Appends displayed lines to a log file, or to a series of numbered files
+/// when rotating.
+pub struct Logger {
+    file: LineWriter<File>,
+    format: LogFormat,
+    written: usize,         // Lines written so far
+    path: PathBuf,          // Path given, which rotated files are numbered after
+    number: usize,          // Number of the current rotated file
+    bytes: u64,             // Written to the current file
+    hour: i64,              // Local hours since the epoch at the last line
+    header: Option<Fields>, // Describes the session at the top of every file
+    started: Instant,       // When the header was written
+    total_bytes: u64,       // Written to every file
+    files: usize,           // Files written to
+    tags: Vec<String>,      // Tags of the ports lines come from, if they are logged
+}
+
+impl Logger {
+    /// Append to `path`, or when rotating, start the first unused numbered
+    /// file after it, e.g. `session.001.log` for `session.log`.
+    pub fn open(path: &Path, format: LogFormat) -> io::Result<Self> {
+        let mut number = 0;
+        let file = match format.rotate {
+            Some(_) => {
+                number = next_free(path, 1);
+                create(&numbered(path, number))?
+            }
+            None => OpenOptions::new().create(true).append(true).open(path)?,
+        };
+        Ok(Logger {
+            file: LineWriter::new(file),
+            format,
+            written: 0,
+            path: path.to_path_buf(),
+            number,
+            bytes: 0,
+            hour: clock::local_hours(SystemTime::now()),
+            header: None,
+            started: Instant::now(),
+            total_bytes: 0,
+            files: 1,
+            tags: Vec::new(),
+        })
+    }
+
+    /// Write a header describing the session, e.g. the port and its
+    /// settings, and repeat it at the top of every later rotated file.
+    pub fn begin(&mut self, fields: Fields) -> io::Result<()> {
+        self.header = Some(fields);
+        self.started = Instant::now();
+        self.write_header()
+    }
+
+    /// Write a footer with the session's totals and any `extra` fields,
+    /// if the log began with a header.
+    pub fn end(&mut self, extra: Fields) -> io::Result<()> {
+        if self.header.is_none() {
+            return Ok(());
+        }
+        let mut fields = vec![
+            ("ended", clock::rfc3339(SystemTime::now())),
+            (
+                "duration",
+                format!("{:.1}s", self.started.elapsed().as_secs_f64()),
+            ),
+            ("lines", self.written.to_string()),
+            ("bytes", self.total_bytes.to_string()),
+            ("files", self.files.to_string()),
+        ];
+        fields.extend(extra);
+        self.write_meta(&fields)?;
+        self.file.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let Some(header) = &self.header else {
+            return Ok(());
+        };
+        let mut fields = vec![("started", clock::rfc3339(SystemTime::now()))];
+        fields.extend(header.iter().cloned());
+        if self.files > 1 {
+            fields.push(("part", self.files.to_string()));
+        }
+        self.write_meta(&fields)
+    }
+
+    /// A header or footer line, which is not numbered, timestamped or
+    /// counted as a line of the log.
+    fn write_meta(&mut self, fields: &[(&'static str, String)]) -> io::Result<()> {
+        let mut out = HEADER_PREFIX.to_string();
+        for (key, value) in fields {
+            out.push_str(&format!(" {}={}", key, quote(value)));
+        }
+        out.push_str(self.format.newline.as_str());
+        self.file.write_all(out.as_bytes())?;
+        self.bytes += out.len() as u64;
+        self.total_bytes += out.len() as u64;
+        Ok(())
+    }
+
+    /// Write each line from port `i` of `--extra-port` after `tags[i]`.
+    pub fn tag_sources(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    /// Write a line from port `source`, after its tag if tags are logged.
+    pub fn write_from(&mut self, line: &str, source: Option<usize>) -> io::Result<()> {
+        match source.and_then(|source| self.tags.get(source)) {
+            Some(tag) => self.write_line(&format!("{} {}", tag, line)),
+            None => self.write_line(line),
+        }
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let mut out = String::new();
+        if self.format.line_numbers {
+            out.push_str(&format!("{} ", self.written + 1));
+        }
+        if self.format.timestamps {
+            out.push_str(&format!("{} ", WallTime::now().hms_millis()));
+        }
+        if self.format.strip_ansi {
+            out.push_str(&text::strip_ansi(line));
+        } else {
+            out.push_str(line);
+        }
+        out.push_str(self.format.newline.as_str());
+
+        self.rotate_for(out.len() as u64)?;
+        self.file.write_all(out.as_bytes())?;
+        self.written += 1;
+        self.bytes += out.len() as u64;
+        self.total_bytes += out.len() as u64;
+        Ok(())
+    }
+
+    /// Move on to the next file first if a line of `len` bytes is due in
+    /// it. The new file is open before the old one is let go, so every line
+    /// lands whole in one of them.
+    fn rotate_for(&mut self, len: u64) -> io::Result<()> {
+        let Some(rotate) = self.format.rotate else {
+            return Ok(());
+        };
+        let hour = clock::local_hours(SystemTime::now());
+        let due = match rotate {
+            // A line longer than the limit still gets a file of its own
+            Rotate::Size(limit) => self.bytes > 0 && self.bytes + len > limit,
+            Rotate::Hourly => hour != self.hour,
+            Rotate::Daily => hour.div_euclid(24) != self.hour.div_euclid(24),
+        };
+        self.hour = hour;
+        if !due {
+            return Ok(());
+        }
+        let number = next_free(&self.path, self.number + 1);
+        let file = create(&numbered(&self.path, number))?;
+        self.file.flush()?;
+        self.file = LineWriter::new(file);
+        self.number = number;
+        self.bytes = 0;
+        self.files += 1;
+        self.write_header()
+    }
+}
+
+/// `value` as is, or in double quotes with `\` and `"` escaped when it is
+/// empty or holds spaces or either of those.
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn create(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// `path` with `number` before its extension, e.g. `session.007.log`.
+fn numbered(path: &Path, number: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{:03}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}.{:03}", stem, number),
+    };
+    path.with_file_name(name)
+}
+
+/// The first number from `from` on whose file does not exist yet, so
+/// earlier sessions' files are never appended to or overwritten.
+fn next_free(path: &Path, from: usize) -> usize {
+    (from..)
+        .find(|&number| !numbered(path, number).exists())
+        .expect("some number is free")
+}
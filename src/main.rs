@@ -1,221 +1,910 @@
-use crossterm::event::{self, Event, KeyCode};
+use app::{App, BlankLines, IdleWatch, LineAge, MemoryCap, SignalWatch, Timestamps, Traffic};
+use bell::Bell;
+use checksum::Checksum;
+use clap::Parser;
+use config::{Config, ErrorLayout, SourceConfig};
+use crossterm::event::{self, DisableBracketedPaste, EnableBracketedPaste, Event};
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use diff::Captures;
+use handoff::PortSettings;
+use highlight::SelectionStyle;
+use keymap::Keymap;
+use line::{MessageSender, ReaderControl};
+use logfile::{Fields, LogFormat, Logger, Newline};
+use options::Options;
+use pipe::Pipe;
+use plot::Plot;
+use port::{PortSource, PortSpec};
+use prefix::Prefix;
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::style::{Color, Style};
-use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Paragraph};
 use ratatui::Terminal;
+use reconnect::Reconnect;
+use regex::Regex;
+use reset::ResetSequence;
+use route::Route;
+use rules::RuleSet;
+use script::Script;
+use serialport::{FlowControl, SerialPort, SerialPortInfo, SerialPortType};
+use session::{RecordFormat, Recorder};
+use skip::Skipper;
+use source::Source;
+use state::State;
 use std::io::{self, stdout};
-use std::sync::mpsc;
-use std::thread;
+use std::path::Path;
 use std::time::Duration;
+use structured::Structured;
+use success::Success;
+use syslog::NumericLevels;
+use text::{Decoding, NulDisplay};
+use transfer::Pacing;
+
+mod app;
+mod baud;
+mod bell;
+mod bench;
+mod capture;
+mod checksum;
+mod classify;
+mod clipboard;
+mod clock;
+mod config;
+mod demo;
+mod diagnostics;
+mod diff;
+mod export;
+mod follow;
+mod handoff;
+mod highlight;
+mod keymap;
+mod line;
+mod logfile;
+mod options;
+mod palette;
+mod pane;
+mod pipe;
+mod plot;
+mod port;
+mod prefix;
+#[cfg(not(feature = "async"))]
+mod reader;
+#[cfg(feature = "async")]
+mod reader_async;
+mod reconnect;
+mod reset;
+mod route;
+mod rules;
+mod script;
+mod search;
+mod session;
+mod skip;
+mod source;
+mod state;
+mod structured;
+mod success;
+mod syslog;
+mod text;
+mod transfer;
+mod ui;
+
+/// Most messages taken from the channel between two frames, so a burst
+/// cannot stall drawing and key handling.
+const MESSAGES_PER_FRAME: usize = 1000;
+
+/// Longest wait for a key before drawing the next frame.
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// Baud rate used when neither `--baud` nor a remembered rate is available.
+const DEFAULT_BAUD: u32 = 115200;
 
 fn main() -> io::Result<()> {
-    let top_perc = 80;
-    let bot_perc = 20;
-    // List available ports
-    let ports = serialport::available_ports().expect("No ports found!");
-    if ports.is_empty() {
-        eprintln!("No available serial ports.");
-        return Ok(());
-    }
+    let options = Options::parse();
 
-    // Connect to the first available port
-    let port_name = &ports[0].port_name;
-    println!("Connecting to {}...", port_name);
+    let config = match Config::load(options.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+    };
+    let mut rules = RuleSet::from_config(&config);
+    rules.loose_keywords |= options.loose_keywords;
+    let compiled = match rules.compile() {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+    };
 
-    let baud_rate = 115200;
-    let timeout = Duration::from_millis(1000);
-    let mut port = serialport::new(port_name, baud_rate)
-        .timeout(timeout)
-        .open()
-        .expect("Failed to open port");
+    let log_format = LogFormat {
+        strip_ansi: options.strip_ansi_log,
+        timestamps: options.log_timestamps,
+        line_numbers: options.log_line_numbers,
+        newline: match options.log_newline.as_str() {
+            "crlf" => Newline::Crlf,
+            "native" => Newline::native(),
+            _ => Newline::Lf,
+        },
+        rotate: options.log_rotate,
+    };
+    let mut log = match &options.log {
+        Some(path) => match Logger::open(path, log_format) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("Failed to open log file {}: {}", path.display(), e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
 
-    println!("Connected to {} at {} baud.", port_name, baud_rate);
+    let mut recorder = None;
+    let recordings = [
+        (&options.record, RecordFormat::Session, "session file"),
+        (&options.capture_raw, RecordFormat::Pcap, "raw capture"),
+    ];
+    for (path, format, what) in recordings {
+        let Some(path) = path else {
+            continue;
+        };
+        if let Err(e) = recorder.get_or_insert_with(Recorder::new).add(path, format) {
+            eprintln!("Failed to create {} {}: {}", what, path.display(), e);
+            return Ok(());
+        }
+    }
 
-    // Channel for sending data from the serial port to the UI
-    let (tx, rx) = mpsc::channel();
-
-    // Spawn a thread to read from the serial port
-    thread::spawn(move || {
-        let mut buffer: [u8; 1024] = [0; 1024];
-        let mut partial_line = String::new();
-        loop {
-            match port.read(&mut buffer) {
-                Ok(bytes_read) => {
-                    if bytes_read > 0 {
-                        let data = String::from_utf8_lossy(&buffer[..bytes_read]);
-                        for chunk in data.split_inclusive(['\r', '\n'].as_ref()) {
-                            // Check if the chunk ends with \n (either alone or with \r before it)
-                            if chunk.ends_with("\n") {
-                                // If it ends with both \r\n, trim \r before sending
-                                partial_line.push_str(chunk.trim_end_matches('\r'));
-
-                                // Send the complete line through the channel
-                                if tx.send(partial_line.clone()).is_err() {
-                                    break;
-                                }
-                                partial_line.clear();
-                            } else {
-                                // Otherwise, accumulate the chunk
-                                partial_line.push_str(chunk);
-                            }
-                        }
-                    }
-                }
-                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
-                    // Ignore timeout errors
-                }
-                Err(_) => {
-                    break;
-                }
+    let mut capture = match &options.capture {
+        Some(path) => match Logger::open(path, log_format) {
+            Ok(log) => Some((path, log)),
+            Err(e) => {
+                eprintln!("Failed to open capture file {}: {}", path.display(), e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    let plot = match &options.plot {
+        Some(pattern) => match Plot::new(pattern, options.plot_window) {
+            Ok(plot) => Some(plot),
+            Err(e) => {
+                eprintln!("{}", e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    let mut script = match &options.init_script {
+        Some(path) => match Script::load(path) {
+            Ok(script) => Some(script),
+            Err(e) => {
+                eprintln!("{}", e);
+                return Ok(());
             }
+        },
+        None => None,
+    };
+
+    let strip_prefix = match Prefix::from_config(&config.strip) {
+        Ok(prefix) => prefix,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
         }
-    });
+    };
 
-    // Initialize the terminal UI
-    enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let checksum = match config.checksum.as_ref().map(Checksum::from_config) {
+        Some(Ok(checksum)) => Some(checksum),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+        None => None,
+    };
 
-    let mut received_data: Vec<String> = Vec::new();
-    let mut error_warn_data: Vec<(String, Color)> = Vec::new(); // Store both message and color
-    let mut scroll_offset = 0;
-    let mut error_warn_scroll_offset = 0; // Add a scroll offset for errors and warnings
-    let mut is_scrolled = false; // Track if user manually scrolled
-    let mut is_error_warn_scrolled = false; // Track if user manually scrolled the error/warn section
+    let bell = match config.bell.as_ref().map(Bell::from_config) {
+        Some(Ok(bell)) => Some(bell),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+        None => None,
+    };
 
-    loop {
-        // Handle UI events
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Down => {
-                        if scroll_offset < received_data.len().saturating_sub(1) {
-                            scroll_offset += 1;
-                            is_scrolled = true;
-                        }
-                    }
-                    KeyCode::Up => {
-                        if scroll_offset > 0 {
-                            scroll_offset -= 1;
-                            is_scrolled = true;
-                        }
-                    }
-                    KeyCode::Char('a') => {
-                        // Reset to auto-scrolling
-                        is_scrolled = false;
-                    }
-                    KeyCode::Char('w') => {
-                        if error_warn_scroll_offset < error_warn_data.len().saturating_sub(1) {
-                            error_warn_scroll_offset += 1;
-                            is_error_warn_scrolled = true;
-                        }
-                    }
-                    KeyCode::Char('s') => {
-                        if error_warn_scroll_offset > 0 {
-                            error_warn_scroll_offset -= 1;
-                            is_error_warn_scrolled = true;
-                        }
-                    }
-                    KeyCode::Char('d') => {
-                        // Reset to auto-scrolling for error/warnings
-                        is_error_warn_scrolled = false;
-                    }
-                    _ => {}
-                }
+    let exit_on = match &options.exit_on {
+        Some(pattern) => match Regex::new(pattern) {
+            Ok(exit_on) => Some(exit_on),
+            Err(e) => {
+                eprintln!("Invalid --exit-on pattern {:?}: {}", pattern, e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+    let success = match &config.success {
+        Some(success) => match Success::from_config(success, exit_on) {
+            Ok(success) => Some(success),
+            Err(e) => {
+                eprintln!("{}", e);
+                return Ok(());
             }
+        },
+        None => exit_on.map(Success::new),
+    };
+
+    let structured = match config.structured.as_ref().map(Structured::from_config) {
+        Some(Ok(structured)) => Some(structured),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+        None => options.structured.then(Structured::new),
+    };
+
+    let numeric_levels = match config
+        .numeric_levels
+        .as_ref()
+        .map(NumericLevels::from_config)
+    {
+        Some(Ok(levels)) => Some(levels),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+        None => options.numeric_levels.then(NumericLevels::new),
+    };
+
+    let selection = match SelectionStyle::from_config(&config.selection) {
+        Ok(selection) => selection,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let routes = match config.route.iter().map(Route::from_config).collect() {
+        Ok(routes) => routes,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
         }
+    };
+
+    let keymap = match Keymap::from_config(&config.keys) {
+        Ok(keymap) => keymap,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let split_presets = match config.split_presets() {
+        Ok(presets) => presets,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let baud_presets = match config.baud_presets() {
+        Ok(presets) => presets,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let prompt = match &options.prompt {
+        Some(pattern) => match Regex::new(pattern) {
+            Ok(prompt) => Some(prompt),
+            Err(e) => {
+                eprintln!("Invalid prompt pattern {:?}: {}", pattern, e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    // Command line options override the config file
+    let send = &config.send;
+    let send_prompt = options.send_prompt.as_ref().or(send.prompt.as_ref());
+    let pacing = Pacing {
+        line_delay: Duration::from_millis(
+            options.send_line_delay.or(send.line_delay_ms).unwrap_or(0),
+        ),
+        byte_delay: Duration::from_millis(
+            options.send_byte_delay.or(send.byte_delay_ms).unwrap_or(0),
+        ),
+        prompt: match (&prompt, send_prompt) {
+            (Some(prompt), _) => Some(prompt.clone()),
+            (None, Some(text)) => Some(Regex::new(&regex::escape(text)).expect("escaped text")),
+            (None, None) => None,
+        },
+        prompt_timeout: options.prompt_timeout,
+        rate: options.send_rate.or(send.rate),
+    };
+    // Only a prompt given as such holds the init script, not a file's --send-prompt
+    if let (Some(script), Some(_)) = (script.as_mut(), &prompt) {
+        script.wait_for_prompts(options.prompt_timeout);
+    }
+    let flow_control = if options.xon_xoff || send.xon_xoff {
+        FlowControl::Software
+    } else {
+        FlowControl::None
+    };
+
+    // Fall back to the port and baud rate of the last session
+    let mut state = State::load();
+    let baud_rate = options.baud.or(state.baud).unwrap_or(DEFAULT_BAUD);
+
+    // Channel for sending data from the serial port to the UI
+    let (tx, mut rx) = line::channel();
+
+    let pipe = match &options.pipe {
+        Some(command) => match Pipe::spawn(command, tx.clone()) {
+            Ok(pipe) => Some(pipe),
+            Err(e) => {
+                eprintln!("Failed to run {:?}: {}", command, e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
 
-        // Receive data from the serial port
-        if let Ok(data) = rx.try_recv() {
-            // Convert data to lowercase to perform case-insensitive comparison
-            let data_lower = data.to_lowercase();
+    let captures = match options.diff.as_deref() {
+        Some([old, new]) => match Captures::read(old, new) {
+            Ok(captures) => Some(captures),
+            Err(e) => {
+                eprintln!("Failed to read captures to diff: {}", e);
+                return Ok(());
+            }
+        },
+        _ => None,
+    };
 
-            // Check if the data contains any variation of "ERR", "ERROR", "WRN", or "WARN"
-            if data_lower.contains("err") || data_lower.contains("error") {
-                error_warn_data.push((data, Color::Red)); // Red color for errors
-            } else if data_lower.contains("wrn") || data_lower.contains("warn") {
-                error_warn_data.push((data, Color::Yellow)); // Yellow color for warnings
-            } else {
-                received_data.push(data);
+    let mut control = ReaderControl::new(match options.charset.as_str() {
+        "latin1" => Decoding::Latin1,
+        "cp437" => Decoding::Cp437,
+        _ => utf8_decoding(&options),
+    });
+    if options.debug {
+        control = control.with_diagnostics();
+    }
+    control.set_show_partial(options.show_partial);
+    if let Some(prompt) = &pacing.prompt {
+        control = control.with_prompt(prompt.clone());
+    }
+    // Tags of the ports read, when there are several
+    let mut sources = Vec::new();
+    let writer = if captures.is_some() {
+        None
+    } else if let Some(path) = &options.replay {
+        control = control.with_replay_speed(options.replay_speed);
+        if let Err(e) = session::replay(path.clone(), control.clone(), tx) {
+            eprintln!("Failed to replay {}: {}", path.display(), e);
+            return Ok(());
+        }
+        None
+    } else if options.bench {
+        bench::spawn(MESSAGES_PER_FRAME, control.clone(), tx);
+        None
+    } else if options.demo {
+        demo::spawn(options.demo_rate, control.clone(), tx);
+        None
+    } else if let Some(path) = &options.follow {
+        follow::spawn(path.clone(), control.clone(), tx);
+        None
+    } else {
+        let setup = PortSetup {
+            baud_rate,
+            flow_control,
+            sources: &config.source,
+        };
+        match connect(&options, setup, recorder, control.clone(), &mut state, tx) {
+            Some((writer, port_sources)) => {
+                sources = port_sources;
+                Some(writer)
             }
+            None => return Ok(()),
         }
+    };
 
-        // Prevent buffers from growing indefinitely
-        if received_data.len() > 1000 {
-            received_data.drain(..received_data.len().saturating_sub(1000));
+    if options.log_header {
+        let header = log_header(&options, writer.as_deref());
+        let logs = [
+            log.as_mut().zip(options.log.as_ref()),
+            capture.as_mut().map(|(path, log)| (log, *path)),
+        ];
+        for (log, path) in logs.into_iter().flatten() {
+            if let Err(e) = log.begin(header.clone()) {
+                eprintln!("Failed to write to {}: {}", path.display(), e);
+                return Ok(());
+            }
         }
-        if error_warn_data.len() > 1000 {
-            error_warn_data.drain(..error_warn_data.len().saturating_sub(1000));
+    }
+
+    // Tags are only logged when asked for
+    if options.log_source_tags {
+        let tags: Vec<String> = sources.iter().map(Source::label).collect();
+        let logs = [log.as_mut(), capture.as_mut().map(|(_, log)| log)];
+        for log in logs.into_iter().flatten() {
+            log.tag_sources(tags.clone());
         }
+    }
 
-        // Auto-scroll to the latest entry if not manually scrolled for the main data
-        if !is_scrolled {
-            let serial_pane_height = terminal.size()?.height as usize * top_perc / 100; // Calculate 70% height
-            scroll_offset = received_data.len().saturating_sub(serial_pane_height);
+    // Headless capture needs none of the UI
+    if let Some((path, log)) = capture {
+        let succeeded = capture::run(
+            path,
+            log,
+            rx,
+            &compiled.classifier,
+            checksum.as_ref(),
+            success.as_ref(),
+            options.skip_initial.map(Skipper::new),
+            options.duration,
+        )?;
+        // Scripts waiting for the device to get ready learn that it did not
+        if !succeeded {
+            std::process::exit(1);
         }
+        return Ok(());
+    }
 
-        // Auto-scroll to the latest entry if not manually scrolled for the error/warning data
-        if !is_error_warn_scrolled {
-            let error_warn_pane_height = terminal.size()?.height as usize * bot_perc / 100; // Calculate 30% height
-            error_warn_scroll_offset = error_warn_data.len().saturating_sub(error_warn_pane_height);
+    let remember = writer.is_some();
+    let reader = captures.is_none().then_some(control);
+    let script = script.filter(|_| writer.is_some());
+    let mut app = App::new();
+    app.writer = writer;
+    app.sources = sources;
+    app.read_only = options.read_only;
+    app.reader = reader;
+    app.utf8 = utf8_decoding(&options);
+    app.baud_rate = baud_rate;
+    app.baud_presets = baud_presets;
+    app.split_presets = split_presets;
+    app.set_split(split_presets[0]);
+    app.keymap = keymap;
+    app.scroll_step = usize::from(options.scroll_step);
+    app.min_size = options.min_size;
+    app.display.compact = options.compact;
+    let error_layout = match options.error_layout.as_deref() {
+        Some("split") => ErrorLayout::Split,
+        Some("inline") => ErrorLayout::Inline,
+        Some(_) => ErrorLayout::Combined,
+        None => config.error_layout,
+    };
+    app.classify = !options.no_classify && error_layout != ErrorLayout::Inline;
+    app.split_warnings = app.classify && error_layout == ErrorLayout::Split;
+    if app.split_warnings {
+        app.error_warn_title = "Errors".to_string();
+    }
+    app.display.wrap = options.wrap;
+    app.display.collapse_runs = options.collapse_runs;
+    app.display.nul = match options.nul.as_str() {
+        "escape" => NulDisplay::Escape,
+        "glyph" => NulDisplay::Glyph,
+        _ => NulDisplay::Raw,
+    };
+    app.display.wrap_glyph = (!options.no_wrap_glyph).then_some(options.wrap_glyph);
+    app.pacing = pacing;
+    app.plot = plot;
+    app.progress_gauge = options.progress_gauge;
+    if options.traffic {
+        app.traffic = Some(Traffic::new(options.traffic_cumulative));
+    }
+    if options.signals && app.writer.is_some() {
+        app.signal_watch = Some(SignalWatch::new());
+    }
+    app.strip_prefix = strip_prefix;
+    app.checksum = checksum;
+    app.bell = bell;
+    app.success = success;
+    app.structured = structured;
+    app.numeric_levels = numeric_levels;
+    app.tab_width = (!options.keep_tabs).then_some(usize::from(options.tab_width));
+    app.unescape_newlines = options.unescape_newlines;
+    app.blank_lines = match options.blank_lines.as_str() {
+        "collapse" => BlankLines::Collapse,
+        "drop" => BlankLines::Drop,
+        _ => BlankLines::Keep,
+    };
+    app.script = script;
+    app.prompt_timeout = prompt.is_some().then_some(options.prompt_timeout);
+    app.macros = state.macros.clone();
+    app.display.deltas = options.deltas;
+    app.display.delta_threshold = Duration::from_millis(options.delta_threshold);
+    if let Some(ms) = options.burst_gap {
+        app.display.timestamps = Timestamps::Bursts;
+        app.display.burst_gap = Duration::from_millis(ms);
+    } else if options.timestamps {
+        app.display.timestamps = Timestamps::Every;
+    }
+    app.log = log;
+    app.pipe = pipe;
+    app.memory = options.max_memory.map(MemoryCap::new);
+    // A diff has nothing to route
+    if captures.is_none() {
+        app.routes = routes;
+        app.route_layout = config.route_layout;
+    }
+    app.use_rules(rules, compiled);
+    if let Some(title) = &config.titles.main {
+        app.main_title = title.clone();
+    }
+    if let Some(title) = &config.titles.errors {
+        app.error_warn_title = title.clone();
+    }
+    if let Some(title) = &config.titles.warnings {
+        app.warnings_title = title.clone();
+    }
+    app.display.responses = options.highlight_responses;
+    app.display.selection = selection;
+    if captures.is_none() {
+        app.idle = options
+            .idle_warn
+            .map(|ms| IdleWatch::new(Duration::from_millis(ms)));
+        app.line_age = options.line_age.then(LineAge::new);
+        app.skip = options.skip_initial.map(Skipper::new);
+    }
+    if let Some(captures) = &captures {
+        app.show_diff(&captures.diff(), &captures.old_name, &captures.new_name);
+    }
+
+    if options.bench {
+        return bench::run(&mut app, &mut rx, MESSAGES_PER_FRAME, INPUT_POLL);
+    }
+
+    // Initialize the terminal UI
+    enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(
+        stdout,
+        crossterm::terminal::EnterAlternateScreen,
+        EnableBracketedPaste
+    )?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    while !app.quit {
+        // Handle UI events
+        if event::poll(INPUT_POLL)? {
+            match event::read()? {
+                Event::Key(key) => app.handle_key(key),
+                Event::Paste(text) => app.handle_paste(text),
+                _ => {}
+            }
+        }
+
+        // Receive data from the serial port, leaving it queued while the UI
+        // is paused, which a success line may do
+        for _ in 0..MESSAGES_PER_FRAME {
+            if app.ui_paused || app.quit {
+                break;
+            }
+            let Some(message) = rx.try_recv() else {
+                break;
+            };
+            app.handle_message(message);
         }
+        app.backlog = rx.queued();
+        if let Some(diagnostics) = app.reader.as_ref().and_then(|reader| reader.diagnostics()) {
+            diagnostics.queued(app.backlog);
+        }
+        app.check_idle();
+        app.poll_transfer();
+        app.poll_script();
+        app.poll_signals();
 
         // Draw the UI
-        terminal.draw(|f| {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Percentage(top_perc as u16), Constraint::Percentage(bot_perc as u16)].as_ref())
-                .split(f.size());
-
-            let text = Paragraph::new(
-                received_data
-                    .iter()
-                    .map(|line| Line::from(Span::styled(line, Style::default().fg(Color::Green))))
-                    .collect::<Vec<Line>>(),
-            )
-            .block(
-                Block::default()
-                    .title("Serial Monitor")
-                    .borders(Borders::ALL),
-            )
-            .scroll((scroll_offset as u16, 0));
-
-            // Combine error and warning data in the same pane, coloring each appropriately
-            let error_warn_text = Paragraph::new(
-                error_warn_data
-                    .iter()
-                    .map(|(line, color)| {
-                        Line::from(Span::styled(line, Style::default().fg(*color)))
-                    })
-                    .collect::<Vec<Line>>(),
-            )
-            .block(
-                Block::default()
-                    .title("Errors and Warnings")
-                    .borders(Borders::ALL),
-            )
-            .scroll((error_warn_scroll_offset as u16, 0)); // Add scrolling for the error/warning pane
-
-            f.render_widget(text, chunks[0]);
-            f.render_widget(error_warn_text, chunks[1]);
-        })?;
+        terminal.draw(|f| ui::draw(f, &mut app))?;
     }
 
     // Restore the terminal
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
+        DisableBracketedPaste,
         crossterm::terminal::LeaveAlternateScreen
     )?;
     terminal.show_cursor()?;
+    if let Some(line) = &app.exit_line {
+        println!("Success: {}", line);
+    }
+
+    if let Some(log) = app.log.as_mut() {
+        if let Err(e) = log.end(Vec::new()) {
+            eprintln!("Failed to finish the log: {}", e);
+        }
+    }
+
+    if let Some(path) = &options.export_html {
+        let mut panes = vec![(app.main_title.as_str(), &app.main)];
+        panes.extend(
+            app.routes
+                .iter()
+                .map(|route| (route.title.as_str(), &route.pane)),
+        );
+        panes.push((app.error_warn_title.as_str(), &app.error_warn));
+        if app.split_warnings {
+            panes.push((app.warnings_title.as_str(), &app.warnings));
+        }
+        if let Err(e) = export::write_html(path, &panes, options.export_timestamps, app.display.nul)
+        {
+            eprintln!("Failed to export {}: {}", path.display(), e);
+        }
+    }
+
+    // Remember a baud rate changed at runtime for the next session
+    if remember && state.baud != Some(app.baud_rate) {
+        state.baud = Some(app.baud_rate);
+        // Macros are saved as they are recorded; keep them
+        state.macros = app.macros.clone();
+        let _ = state.save();
+    }
 
     Ok(())
 }
 
+/// Fields of the log header saying where the lines come from: the port and
+/// its settings as read back from `port`, or the file or mode used instead.
+fn log_header(options: &Options, port: Option<&dyn SerialPort>) -> Fields {
+    let file = |source: &str, path: &Path| {
+        vec![
+            ("source", source.to_string()),
+            ("file", path.display().to_string()),
+        ]
+    };
+    if let Some(path) = &options.replay {
+        return file("replay", path);
+    }
+    if let Some(path) = &options.follow {
+        return file("follow", path);
+    }
+    let Some(port) = port else {
+        let source = if options.demo {
+            "demo"
+        } else if options.bench {
+            "bench"
+        } else {
+            "diff"
+        };
+        return vec![("source", source.to_string())];
+    };
+    let mut fields = vec![("source", "serial".to_string())];
+    match PortSettings::of(port) {
+        Ok(settings) => {
+            let flow = match settings.flow_control {
+                FlowControl::None => "none",
+                FlowControl::Software => "software",
+                FlowControl::Hardware => "hardware",
+            };
+            fields.extend([
+                ("port", settings.name.clone()),
+                ("baud", settings.baud_rate.to_string()),
+                ("framing", settings.framing()),
+                ("flow", flow.to_string()),
+            ]);
+        }
+        Err(_) => fields.push(("port", port.name().unwrap_or_default())),
+    }
+    fields
+}
+
+fn utf8_decoding(options: &Options) -> Decoding {
+    if options.escape_invalid {
+        Decoding::Escape
+    } else {
+        Decoding::Lossy
+    }
+}
+
+/// How every port read is opened, and how their lines are told apart.
+struct PortSetup<'a> {
+    baud_rate: u32,
+    flow_control: FlowControl,
+    sources: &'a [SourceConfig], // Tags and colors of the ports, from the config
+}
+
+/// Open the port used last time if it is still there, otherwise the first
+/// available one, and start reading it, and any `--extra-port`, into `tx`.
+///
+/// Returns a handle for sending to the first port and, with extra ports, how
+/// each port's lines are tagged; or `None` after reporting why no port could
+/// be opened. The first port and the rate are saved to `state`.
+fn connect(
+    options: &Options,
+    setup: PortSetup,
+    recorder: Option<Recorder>,
+    control: ReaderControl,
+    state: &mut State,
+    tx: MessageSender,
+) -> Option<(Box<dyn SerialPort>, Vec<Source>)> {
+    let PortSetup {
+        baud_rate,
+        flow_control,
+        sources,
+    } = setup;
+    if options.shared && cfg!(windows) {
+        eprintln!("--shared is not available on Windows, which lets only one program open a serial port at a time");
+        return None;
+    }
+    let reset = match &options.reset_on_connect {
+        Some(sequence) => {
+            let pulse = Duration::from_millis(options.reset_pulse);
+            match ResetSequence::parse(sequence, pulse) {
+                Ok(reset) => Some(reset),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return None;
+                }
+            }
+        }
+        None => None,
+    };
+
+    // List available ports
+    let ports = serialport::available_ports().expect("No ports found!");
+    if ports.is_empty() {
+        eprintln!("No available serial ports.");
+        return None;
+    }
+
+    let port_name = match &options.usb_serial {
+        Some(serial) => match find_usb_serial(&ports, serial) {
+            Some(name) => name,
+            None => {
+                eprintln!("No USB serial port with serial number {:?}.", serial);
+                let serials = usb_serials(&ports);
+                if !serials.is_empty() {
+                    eprintln!("Available serial numbers: {}", serials.join(", "));
+                }
+                return None;
+            }
+        },
+        // Device enumeration order can change between runs, so prefer the
+        // remembered port by name
+        None => ports
+            .iter()
+            .map(|port| &port.port_name)
+            .find(|&name| state.port.as_ref() == Some(name))
+            .unwrap_or(&ports[0].port_name),
+    };
+    println!("Connecting to {}...", port_name);
+
+    // With --extra-port, every port's lines are tagged with where they came from
+    let names: Vec<String> = std::iter::once(port_name.clone())
+        .chain(options.extra_port.iter().cloned())
+        .collect();
+    let sources = if options.extra_port.is_empty() {
+        Vec::new()
+    } else {
+        match Source::resolve(&names, sources) {
+            Ok(sources) => sources,
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        }
+    };
+    let specs: Vec<PortSpec> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let builder = serialport::new(name, baud_rate)
+                .timeout(Duration::from_millis(options.read_timeout))
+                .flow_control(flow_control);
+            #[cfg(unix)]
+            let builder = builder.exclusive(!options.shared);
+            PortSpec {
+                builder,
+                baud_rate,
+                custom_baud: options.custom_baud,
+                read_buffer: options.read_buffer as usize,
+                silence_limit: options.reconnect_on_silence,
+                source: sources.get(index).map(|source| PortSource {
+                    index,
+                    tag: source.tag.clone(),
+                }),
+            }
+        })
+        .collect();
+    let reconnect = Reconnect {
+        attempts: options.reconnect_attempts,
+        delay: Duration::from_millis(options.reconnect_delay),
+        backoff: options.reconnect_backoff,
+    };
+
+    // Only the port sent to is recorded, so a replay is of one device.
+    // Failures carry the index of the port that could not be opened.
+    #[cfg(not(feature = "async"))]
+    let result = specs
+        .iter()
+        .enumerate()
+        .try_fold(None, |writer, (index, spec)| {
+            let port = spec.open().map_err(|e| (index, e))?;
+            // Keep a second handle to the first port for sending
+            let writer = match writer {
+                Some(writer) => writer,
+                None => port.try_clone().map_err(|e| (index, e))?,
+            };
+            let recorder = recorder.clone().filter(|_| index == 0);
+
+            // Spawn a thread to read from each serial port
+            reader::spawn(
+                port,
+                spec.clone(),
+                reconnect,
+                control.clone(),
+                recorder,
+                tx.clone(),
+            );
+            Ok(Some(writer))
+        });
+    #[cfg(not(feature = "async"))]
+    let result = result.map(|writer| writer.expect("at least one port"));
+
+    #[cfg(feature = "async")]
+    let result = reader_async::spawn(specs, reconnect, control, recorder, tx)
+        .map(|mut writers| writers.remove(0));
+
+    match result {
+        Ok(mut writer) => {
+            println!("Connected to {} at {} baud.", names.join(", "), baud_rate);
+            // The reader is already running, so the boot output is not missed
+            if let Some(reset) = reset {
+                match reset.run(writer.as_mut()) {
+                    Ok(()) => println!("Reset the board."),
+                    Err(e) => eprintln!("Failed to reset the board: {}", e),
+                }
+            }
+            state.port = Some(port_name.clone());
+            state.baud = Some(baud_rate);
+            let _ = state.save();
+            Some((writer, sources))
+        }
+        Err((index, e)) => {
+            eprintln!(
+                "Failed to open {}: {}",
+                names[index],
+                baud::describe_error(baud_rate, &e)
+            );
+            // The kind serialport gives a port another program has locked
+            if e.kind() == serialport::ErrorKind::NoDevice {
+                if cfg!(windows) {
+                    eprintln!("The port may be open in another program; Windows lets only one program open a serial port at a time.");
+                } else {
+                    eprintln!("The port may be open in another program. To watch it from a second aserial, start both with --shared.");
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Name of the port whose USB descriptor has serial number `serial`.
+fn find_usb_serial<'a>(ports: &'a [SerialPortInfo], serial: &str) -> Option<&'a String> {
+    ports
+        .iter()
+        .find(|port| match &port.port_type {
+            SerialPortType::UsbPort(info) => info.serial_number.as_deref() == Some(serial),
+            _ => false,
+        })
+        .map(|port| &port.port_name)
+}
+
+/// Serial numbers of the USB ports present, for suggesting a match.
+fn usb_serials(ports: &[SerialPortInfo]) -> Vec<String> {
+    ports
+        .iter()
+        .filter_map(|port| match &port.port_type {
+            SerialPortType::UsbPort(info) => info
+                .serial_number
+                .as_ref()
+                .map(|serial| format!("{} ({})", serial, port.port_name)),
+            _ => None,
+        })
+        .collect()
+}
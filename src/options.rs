@@ -0,0 +1,394 @@
+use crate::logfile::{parse_rotate, Rotate};
+use crate::port::MAX_READ_BUFFER;
+use crate::skip::{parse_skip, SkipInitial};
+use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Command line options.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Options {
+    /// Baud rate to open the port at [default: the last used rate, or 115200]
+    #[arg(short, long)]
+    pub baud: Option<u32>,
+
+    /// Open the USB serial adapter with this serial number instead of the last used port
+    #[arg(long, value_name = "SN")]
+    pub usb_serial: Option<String>,
+
+    /// If the driver rejects the baud rate, set it directly with termios2 (Linux only)
+    #[arg(long)]
+    pub custom_baud: bool,
+
+    /// Also read PORT, at the same settings, tagging each line with the port it came from; repeat for more ports
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["diff", "follow", "replay", "demo"])]
+    pub extra_port: Vec<String>,
+
+    /// Open the port without locking other programs out, so a second aserial started with --shared can open it too (not on Windows)
+    #[arg(long)]
+    pub shared: bool,
+
+    /// Never write to the port: no sending, no baud rate changes, no reset
+    #[arg(long, conflicts_with_all = ["init_script", "reset_on_connect"])]
+    pub read_only: bool,
+
+    /// Reset the board after connecting by toggling DTR/RTS, e.g. `dtr=0,rts=1,wait,rts=0` (the default)
+    #[arg(long, value_name = "SEQUENCE", num_args = 0..=1, default_missing_value = crate::reset::DEFAULT_SEQUENCE)]
+    pub reset_on_connect: Option<String>,
+
+    /// Milliseconds each `wait` step of --reset-on-connect lasts
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    pub reset_pulse: u64,
+
+    /// Give up reconnecting to a lost port after N attempts (0 retries forever)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub reconnect_attempts: u32,
+
+    /// Milliseconds to wait before each reconnection attempt
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    pub reconnect_delay: u64,
+
+    /// Double the reconnection delay after every failed attempt, up to 30s
+    #[arg(long)]
+    pub reconnect_backoff: bool,
+
+    /// Reopen the port after this long without data, e.g. 30s, for adapters that go quiet instead of failing when unplugged
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub reconnect_on_silence: Option<Duration>,
+
+    /// Milliseconds a single read of the port waits for data before trying again
+    #[arg(long, value_name = "MS", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(10..=60000))]
+    pub read_timeout: u64,
+
+    /// Show the number of bytes received and sent in the main pane title
+    #[arg(long)]
+    pub traffic: bool,
+
+    /// Keep the --traffic counters running when the panes are cleared
+    #[arg(long, requires = "traffic")]
+    pub traffic_cumulative: bool,
+
+    /// Show the CTS, DSR, CD and RI input lines in the main pane title
+    #[arg(long)]
+    pub signals: bool,
+
+    /// Warn in the error pane when no data has arrived for MS milliseconds
+    #[arg(long, value_name = "MS")]
+    pub idle_warn: Option<u64>,
+
+    /// Drop the first N lines, or the lines in the first DURATION (e.g. 500ms), after each connect
+    #[arg(long, value_name = "N|DURATION", value_parser = parse_skip)]
+    pub skip_initial: Option<SkipInitial>,
+
+    /// Show how long ago the last line arrived in the bottom row
+    #[arg(long)]
+    pub line_age: bool,
+
+    /// Start with the lines mentioning the last command sent highlighted (toggle with h)
+    #[arg(long)]
+    pub highlight_responses: bool,
+
+    /// Drop old lines sooner and warn once the panes hold about MB megabytes
+    #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_memory: Option<u64>,
+
+    /// Lines moved by each press of the arrow keys; + and - change it at runtime
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub scroll_step: u16,
+
+    /// Smallest terminal, as COLSxROWS, to draw the panes in; smaller ones show a note instead
+    #[arg(long, value_name = "COLSxROWS", default_value = "20x8", value_parser = parse_size)]
+    pub min_size: (u16, u16),
+
+    /// Start without pane borders, to fit more lines on small terminals
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Show runs of at least N identical bytes as one, e.g. `00 × 4096` (0 never)
+    #[arg(long, value_name = "N", default_value_t = 256)]
+    pub collapse_runs: usize,
+
+    /// Show NUL bytes as received, as the escape \x00, or as the glyph ␀, on screen and in --export-html
+    #[arg(long, value_name = "MODE", default_value = "raw", value_parser = ["raw", "escape", "glyph"])]
+    pub nul: String,
+
+    /// Start with long lines wrapped instead of cut off
+    #[arg(long)]
+    pub wrap: bool,
+
+    /// Character marking where a line wraps
+    #[arg(long, value_name = "CHAR", default_value_t = '↵')]
+    pub wrap_glyph: char,
+
+    /// Wrap lines without marking where
+    #[arg(long)]
+    pub no_wrap_glyph: bool,
+
+    /// Start with timing deltas shown
+    #[arg(long)]
+    pub deltas: bool,
+
+    /// Only show timing deltas for gaps longer than MS milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    pub delta_threshold: u64,
+
+    /// Start with the time each line arrived shown
+    #[arg(long)]
+    pub timestamps: bool,
+
+    /// Start with only the first line of each burst timestamped, bursts being split by gaps over MS milliseconds
+    #[arg(long, value_name = "MS")]
+    pub burst_gap: Option<u64>,
+
+    /// Show bytes that are not valid UTF-8 as \xNN escapes instead of �
+    #[arg(long)]
+    pub escape_invalid: bool,
+
+    /// Decode received bytes as this character set; `u` cycles it at runtime
+    #[arg(long, value_name = "CHARSET", default_value = "utf8", value_parser = ["utf8", "latin1", "cp437"])]
+    pub charset: String,
+
+    /// When sending a file, pause MS milliseconds after each line [default: 0]
+    #[arg(long, value_name = "MS")]
+    pub send_line_delay: Option<u64>,
+
+    /// When sending a file, pause MS milliseconds after each byte [default: 0]
+    #[arg(long, value_name = "MS")]
+    pub send_byte_delay: Option<u64>,
+
+    /// When sending a file, wait for a received line containing TEXT after each line
+    #[arg(long, value_name = "TEXT")]
+    pub send_prompt: Option<String>,
+
+    /// Regex matching the device's prompt; file sends and --init-script wait for it after each line
+    #[arg(long, value_name = "REGEX", conflicts_with = "send_prompt")]
+    pub prompt: Option<String>,
+
+    /// Longest wait for the prompt before a file send or init script stops
+    #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = parse_duration)]
+    pub prompt_timeout: Duration,
+
+    /// When sending a file, send at most BYTES bytes per second
+    #[arg(long, value_name = "BYTES")]
+    pub send_rate: Option<u32>,
+
+    /// Read up to BYTES from the port at a time; larger reads keep up better with fast links
+    #[arg(long, value_name = "BYTES", default_value_t = 4096, value_parser = clap::value_parser!(u32).range(64..=MAX_READ_BUFFER as i64))]
+    pub read_buffer: u32,
+
+    /// Show a pane with the reader's read sizes, timeouts, reconnects and queued messages
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Use XON/XOFF software flow control, pausing output while the device sends XOFF
+    #[arg(long)]
+    pub xon_xoff: bool,
+
+    /// Expand tabs in received lines to the next multiple of N columns
+    #[arg(long, value_name = "N", default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    pub tab_width: u16,
+
+    /// Keep tabs as received instead of expanding them
+    #[arg(long)]
+    pub keep_tabs: bool,
+
+    /// Show `\n` escapes in received lines (e.g. in single-line JSON) as line breaks; the log keeps them
+    #[arg(long)]
+    pub unescape_newlines: bool,
+
+    /// Start showing the line still being received before its newline arrives
+    #[arg(long)]
+    pub show_partial: bool,
+
+    /// Keep received blank lines, collapse each run of them into one, or drop them from the display
+    #[arg(long, value_name = "POLICY", default_value = "keep", value_parser = ["keep", "collapse", "drop"])]
+    pub blank_lines: String,
+
+    /// Show a progress bar while a line redrawn with carriage returns ends in a percentage
+    #[arg(long)]
+    pub progress_gauge: bool,
+
+    /// Plot the number captured by the first group of REGEX in a pane
+    #[arg(long, value_name = "REGEX")]
+    pub plot: Option<String>,
+
+    /// Number of recent values the plot shows
+    #[arg(long, value_name = "N", default_value_t = 200)]
+    pub plot_window: usize,
+
+    /// Show every line in one full-height pane, without sorting out errors and warnings
+    #[arg(long)]
+    pub no_classify: bool,
+
+    /// Show errors and warnings in one pane, in two side by side, or inline in the main pane
+    #[arg(long, value_name = "LAYOUT", value_parser = ["combined", "split", "inline"])]
+    pub error_layout: Option<String>,
+
+    /// Classify `key=value` lines such as `level=error msg="boom"` by their level key
+    #[arg(long)]
+    pub structured: bool,
+
+    /// Classify lines starting with a syslog-style level such as `<3>` by that level
+    #[arg(long)]
+    pub numeric_levels: bool,
+
+    /// Let the built-in error/warning keywords match inside other words, e.g. "ferry"
+    #[arg(long)]
+    pub loose_keywords: bool,
+
+    /// Write lines to FILE without the terminal UI, until --duration passes or Ctrl-C
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["diff", "log"])]
+    pub capture: Option<PathBuf>,
+
+    /// Stop a --capture after this long, e.g. 30s, 5m or 500ms
+    #[arg(long, value_name = "DURATION", requires = "capture", value_parser = parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// Quit with exit status 0 once a received line matches REGEX, e.g. "boot complete"
+    #[arg(long, value_name = "REGEX")]
+    pub exit_on: Option<String>,
+
+    /// Show a line-based diff of two captures instead of opening a serial port
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    pub diff: Option<Vec<PathBuf>>,
+
+    /// Send the commands in FILE once after connecting; `@delay 500ms` lines pause
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["diff", "follow", "capture"])]
+    pub init_script: Option<PathBuf>,
+
+    /// Also write every received line to the stdin of COMMAND, run in the shell
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["diff", "capture"])]
+    pub pipe: Option<String>,
+
+    /// Record every chunk received, with its timing, to a session FILE for --replay
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["diff", "follow", "replay"])]
+    pub record: Option<PathBuf>,
+
+    /// Write every chunk received, with microsecond timestamps, to a pcap FILE for analysis tools
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["diff", "follow", "replay"])]
+    pub capture_raw: Option<PathBuf>,
+
+    /// Play back a session recorded with --record with its original timing, instead of opening a serial port
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["diff", "follow", "init_script"])]
+    pub replay: Option<PathBuf>,
+
+    /// Speed to start a replay at, as a multiple of the recorded pace (e.g. 4 or 0.5)
+    #[arg(long, value_name = "N", default_value_t = 1.0, requires = "replay", value_parser = parse_speed)]
+    pub replay_speed: f64,
+
+    /// Show generated test lines instead of opening a serial port, for trying the UI
+    #[arg(long, hide = true, conflicts_with_all = ["diff", "follow", "replay", "record", "capture_raw", "init_script"])]
+    pub demo: bool,
+
+    /// Lines per second generated by --demo
+    #[arg(
+        long,
+        hide = true,
+        value_name = "N",
+        default_value_t = 10,
+        requires = "demo"
+    )]
+    pub demo_rate: u32,
+
+    /// Measure the highest line rate the UI keeps up with, using demo lines and offscreen frames, then exit
+    #[arg(long, hide = true, conflicts_with_all = ["demo", "diff", "follow", "replay", "capture", "pipe", "init_script"])]
+    pub bench: bool,
+
+    /// Follow lines appended to FILE, like `tail -f`, instead of opening a serial port
+    #[arg(long, value_name = "FILE")]
+    pub follow: Option<PathBuf>,
+
+    /// Read settings from this file instead of the default config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Append every displayed line to this file
+    #[arg(long, value_name = "FILE")]
+    pub log: Option<PathBuf>,
+
+    /// On quit, write both panes to this file as a colored HTML document
+    #[arg(long, value_name = "PATH")]
+    pub export_html: Option<PathBuf>,
+
+    /// Prefix each line of the HTML export with the time it arrived
+    #[arg(long, requires = "export_html")]
+    pub export_timestamps: bool,
+
+    /// Remove ANSI escape sequences from lines before writing them to the log
+    #[arg(long, overrides_with = "keep_ansi_log")]
+    pub strip_ansi_log: bool,
+
+    /// Write ANSI escape sequences to the log as received (the default)
+    #[arg(long, overrides_with = "strip_ansi_log")]
+    pub keep_ansi_log: bool,
+
+    /// Prefix each line written to the log with the time, whether or not the screen shows it
+    #[arg(long)]
+    pub log_timestamps: bool,
+
+    /// Prefix each line written to the log with its number in the log
+    #[arg(long)]
+    pub log_line_numbers: bool,
+
+    /// Keep the source tag of each line in the log (or --capture file) with --extra-port
+    #[arg(long, requires = "extra_port")]
+    pub log_source_tags: bool,
+
+    /// End each line in the log with LF, CRLF or the platform's native terminator
+    #[arg(long, value_name = "NEWLINE", default_value = "lf", value_parser = ["lf", "crlf", "native"])]
+    pub log_newline: String,
+
+    /// Start the log (or --capture file) with a line describing the port and session, and end it with one of totals
+    #[arg(long)]
+    pub log_header: bool,
+
+    /// Split the log (or --capture file) into numbered files by size (e.g. 10MB), hourly or daily
+    #[arg(long, value_name = "SIZE|hourly|daily", value_parser = parse_rotate)]
+    pub log_rotate: Option<Rotate>,
+}
+
+/// A terminal size such as `80x24`.
+fn parse_size(text: &str) -> Result<(u16, u16), String> {
+    let (cols, rows) = text
+        .split_once(['x', 'X', '×'])
+        .ok_or_else(|| format!("expected a size like 20x8, not {:?}", text))?;
+    match (cols.trim().parse(), rows.trim().parse()) {
+        (Ok(cols), Ok(rows)) => Ok((cols, rows)),
+        _ => Err(format!("expected a size like 20x8, not {:?}", text)),
+    }
+}
+
+/// Parse `500ms`, `30s`, `5m` or `1h`; a bare number is seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("expected a duration like 30s, not {:?}", text))?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("unknown unit {:?}, expected ms, s, m or h", unit)),
+    };
+    // Beyond what a Duration holds, which is far longer than anyone waits
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("expected a duration like 30s, not {:?}", text))
+}
+
+/// Parse a replay speed such as `4`, `4x` or `0.5`.
+fn parse_speed(text: &str) -> Result<f64, String> {
+    let speed: f64 = text
+        .strip_suffix(['x', 'X'])
+        .unwrap_or(text)
+        .parse()
+        .map_err(|_| format!("expected a speed like 4 or 0.5, not {:?}", text))?;
+    if !(0.01..=1000.0).contains(&speed) {
+        return Err(format!("{} is not a speed from 0.01 to 1000", text));
+    }
+    Ok(speed)
+}
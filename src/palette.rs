@@ -0,0 +1,185 @@
+use crossterm::event::KeyCode;
+
+/// What running a palette command does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// The same as pressing this key.
+    Key(KeyCode),
+    GotoLine,
+    ToggleCaseSensitive,
+    CycleSearchScope,
+    ToggleProgressGauge,
+    ResetTraffic,
+    FollowBoth,
+    ClearBoth,
+    CopyPortCommands,
+    CancelTransfer,
+}
+
+/// An entry in the command palette.
+pub struct Command {
+    pub name: &'static str,
+    pub key: &'static str, // Key bound to the same action by default, if any
+    pub action: Action,
+}
+
+impl Command {
+    /// Name used for the command in the `[keys]` config section, e.g.
+    /// `clear_focused_pane`.
+    pub fn id(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| match c {
+                ' ' | '-' => '_',
+                c => c.to_ascii_lowercase(),
+            })
+            .collect()
+    }
+}
+
+const fn key(name: &'static str, key: &'static str, code: char) -> Command {
+    named(name, key, KeyCode::Char(code))
+}
+
+const fn named(name: &'static str, key: &'static str, code: KeyCode) -> Command {
+    Command {
+        name,
+        key,
+        action: Action::Key(code),
+    }
+}
+
+const fn unbound(name: &'static str, action: Action) -> Command {
+    Command {
+        name,
+        key: "",
+        action,
+    }
+}
+
+/// Every action the palette offers, in the order shown for an empty query.
+pub const COMMANDS: &[Command] = &[
+    key("Search", "/", '/'),
+    key("Next match", "]", ']'),
+    key("Previous match", "[", '['),
+    key("Save matching lines", "X", 'X'),
+    key("Record macro", "Q", 'Q'),
+    Command {
+        name: "Go to line",
+        key: ":N",
+        action: Action::GotoLine,
+    },
+    key("Jump to last error", "e", 'e'),
+    key("Next error entry", "j", 'j'),
+    key("Previous error entry", "k", 'k'),
+    named("Switch pane", "Tab", KeyCode::Tab),
+    named("Scroll up", "Up", KeyCode::Up),
+    named("Scroll down", "Down", KeyCode::Down),
+    named("Page up", "PgUp", KeyCode::PageUp),
+    named("Page down", "PgDn", KeyCode::PageDown),
+    named("Scroll to oldest line", "Home", KeyCode::Home),
+    named("Follow focused pane", "End", KeyCode::End),
+    key("Scroll error pane down", "w", 'w'),
+    key("Scroll error pane up", "s", 's'),
+    key("Increase scroll step", "+", '+'),
+    key("Decrease scroll step", "-", '-'),
+    key("Follow main pane", "a", 'a'),
+    key("Follow error pane", "d", 'd'),
+    unbound("Follow both panes", Action::FollowBoth),
+    key("Freeze main pane", "z", 'z'),
+    key("Freeze error pane", "Z", 'Z'),
+    key("Pause display", "p", 'p'),
+    key("Pause reading", "P", 'P'),
+    named("Pause replay", "Space", KeyCode::Char(' ')),
+    key("Replay faster", "}", '}'),
+    key("Replay slower", "{", '{'),
+    key("Clear focused pane", "C", 'C'),
+    unbound("Clear both panes", Action::ClearBoth),
+    key("Send line", "t", 't'),
+    key("Send file", "F", 'F'),
+    unbound("Cancel sending file", Action::CancelTransfer),
+    key("Change baud rate", "B", 'B'),
+    key("Cycle decoding", "u", 'u'),
+    key("Cycle control characters", "c", 'c'),
+    key("Toggle line numbers", "n", 'n'),
+    key("Toggle byte counts", "l", 'l'),
+    key("Toggle response highlighting", "h", 'h'),
+    key("Toggle borders", "b", 'b'),
+    key("Switch split preset", "=", '='),
+    key("Grow main pane", ">", '>'),
+    key("Shrink main pane", "<", '<'),
+    key("Toggle wrapping", "W", 'W'),
+    key("Toggle timing deltas", "D", 'D'),
+    key("Toggle heat column", "H", 'H'),
+    key("Toggle partial line", "R", 'R'),
+    key("Toggle scroll lock", "A", 'A'),
+    key("Cycle timestamps", "T", 'T'),
+    unbound("Toggle progress gauge", Action::ToggleProgressGauge),
+    unbound("Toggle case-sensitive search", Action::ToggleCaseSensitive),
+    unbound("Cycle search scope", Action::CycleSearchScope),
+    unbound("Reset traffic counters", Action::ResetTraffic),
+    key("Insert mark", "m", 'm'),
+    key("Insert labelled mark", "M", 'M'),
+    key("Toggle lines since mark", "O", 'O'),
+    key("Select lines", "v", 'v'),
+    key("Toggle inspect mode", "I", 'I'),
+    key("Copy error pane", "Y", 'Y'),
+    unbound("Copy port settings commands", Action::CopyPortCommands),
+    key("Save rules", "S", 'S'),
+    key("Load rules", "L", 'L'),
+    key("Command palette", ":", ':'),
+    key("Quit", "q", 'q'),
+];
+
+/// The open command palette.
+#[derive(Clone, Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize, // Index into `matches()`
+}
+
+impl Palette {
+    /// Commands matching the query, best first.
+    pub fn matches(&self) -> Vec<&'static Command> {
+        let mut scored: Vec<_> = COMMANDS
+            .iter()
+            .filter_map(|command| Some((fuzzy_score(&self.query, command.name)?, command)))
+            .collect();
+        // Stable, so ties keep the order of `COMMANDS`
+        scored.sort_by_key(|&(score, _)| -score);
+        scored.into_iter().map(|(_, command)| command).collect()
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
+
+/// How well `query` matches `name` as a case-insensitive subsequence, higher
+/// for letters at word starts and in runs; `None` if it does not match.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous = None;
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_ascii_lowercase();
+        let found = next + name[next..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if found == 0 || !name[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 2;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
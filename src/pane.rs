@@ -0,0 +1,236 @@
+use crate::classify::{Class, Severity};
+use crate::highlight::Highlight;
+use ratatui::style::Color;
+use std::mem;
+use std::time::{Duration, SystemTime};
+
+/// Default number of lines a pane keeps before dropping the oldest.
+pub const MAX_LINES: usize = 1000;
+
+/// One line held by a pane.
+#[derive(Clone, Debug)]
+pub struct PaneLine {
+    pub text: String,
+    pub color: Color,
+    pub time: SystemTime,           // When the line was added
+    pub severity: Option<Severity>, // Error or warning, as classified on arrival
+    pub raw: Option<Vec<u8>>,       // Bytes the line was received as, for the inspector
+    pub source: Option<usize>,      // Port it came from, when several are read
+}
+
+impl PaneLine {
+    pub fn new(text: String, color: Color) -> Self {
+        PaneLine {
+            text,
+            color,
+            time: SystemTime::now(),
+            severity: None,
+            raw: None,
+            source: None,
+        }
+    }
+
+    /// Approximate memory held by the line.
+    pub fn bytes(&self) -> usize {
+        mem::size_of::<Self>() + self.text.capacity() + self.raw.as_ref().map_or(0, Vec::capacity)
+    }
+}
+
+/// A scrollable list of coloured lines.
+pub struct Pane {
+    pub lines: Vec<PaneLine>,
+    pub scroll_offset: usize,
+    pub is_scrolled: bool,          // Track if user manually scrolled
+    pub trimmed: usize,             // Lines dropped from the front so far
+    pub height: usize,              // Rows visible at the last auto-scroll
+    pub highlights: Vec<Highlight>, // Colour rules applied when drawing
+    pub capacity: usize,            // Lines kept before the oldest are dropped
+    pub bytes: usize,               // Approximate memory held by `lines`
+    pub frozen: Option<usize>,      // Lines received when frozen, while the view is held
+    pub hide_before: Option<usize>, // Absolute line before which nothing is shown, if set
+}
+
+impl Pane {
+    pub fn new() -> Self {
+        Pane {
+            lines: Vec::new(),
+            scroll_offset: 0,
+            is_scrolled: false,
+            trimmed: 0,
+            height: 0,
+            highlights: Vec::new(),
+            capacity: MAX_LINES,
+            bytes: 0,
+            frozen: None,
+            hide_before: None,
+        }
+    }
+
+    pub fn push(&mut self, line: String, color: Color) {
+        self.push_line(PaneLine::new(line, color));
+    }
+
+    /// Add an error or warning, remembering which it is.
+    pub fn push_class(&mut self, line: String, class: Class) {
+        self.push_line(PaneLine {
+            severity: Some(class.severity),
+            ..PaneLine::new(line, class.color)
+        });
+    }
+
+    pub fn push_line(&mut self, line: PaneLine) {
+        self.bytes += line.bytes();
+        self.lines.push(line);
+
+        // Prevent buffers from growing indefinitely
+        if self.lines.len() > self.capacity {
+            self.drop_oldest(self.lines.len() - self.capacity);
+        }
+    }
+
+    /// The line at absolute position `line`, if it is still held.
+    pub fn get(&self, line: usize) -> Option<&PaneLine> {
+        self.lines.get(line.checked_sub(self.trimmed)?)
+    }
+
+    /// Change the text of the line at absolute position `line`, if it is
+    /// still held.
+    pub fn replace(&mut self, line: usize, text: String) {
+        let Some(line) = line
+            .checked_sub(self.trimmed)
+            .and_then(|i| self.lines.get_mut(i))
+        else {
+            return;
+        };
+        self.bytes -= line.bytes();
+        line.text = text;
+        self.bytes += line.bytes();
+    }
+
+    /// Remove the line at absolute position `line`, if it is still held.
+    pub fn remove(&mut self, line: usize) {
+        let Some(i) = line.checked_sub(self.trimmed) else {
+            return;
+        };
+        if i >= self.lines.len() {
+            return;
+        }
+        self.bytes -= self.lines.remove(i).bytes();
+        if self.scroll_offset > i {
+            // Keep the same lines in view
+            self.scroll_offset -= 1;
+        }
+    }
+
+    /// Drop up to `count` of the oldest lines.
+    pub fn drop_oldest(&mut self, count: usize) {
+        let count = count.min(self.lines.len());
+        let freed: usize = self.lines.drain(..count).map(|line| line.bytes()).sum();
+        self.bytes -= freed;
+        self.trimmed += count;
+        if self.frozen.is_some() {
+            // Keep the same lines in view
+            self.scroll_offset = self.scroll_offset.saturating_sub(count);
+        }
+    }
+
+    /// Hold the view still as new lines arrive, or let it move again.
+    pub fn toggle_freeze(&mut self) {
+        self.frozen = match self.frozen {
+            Some(_) => None,
+            None => Some(self.trimmed + self.lines.len()),
+        };
+    }
+
+    /// Number of lines that arrived within `window` either side of line `i`,
+    /// itself included.
+    pub fn density(&self, i: usize, window: Duration) -> usize {
+        let time = self.lines[i].time;
+        let start = time.checked_sub(window).unwrap_or(time);
+        let end = time + window;
+        let first = self.lines.partition_point(|line| line.time < start);
+        let last = self.lines.partition_point(|line| line.time <= end);
+        last.saturating_sub(first)
+    }
+
+    /// Tag shown in the pane title while frozen.
+    pub fn freeze_label(&self) -> String {
+        match self.frozen {
+            Some(total) => format!(
+                " [FROZEN, {} new]",
+                (self.trimmed + self.lines.len()).saturating_sub(total)
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Tag shown in the pane title while older lines are hidden.
+    pub fn hidden_label(&self) -> &'static str {
+        match self.hide_before {
+            Some(_) => " [SINCE MARK]",
+            None => "",
+        }
+    }
+
+    /// Index of the oldest line shown, past any hidden ones.
+    pub fn first_shown(&self) -> usize {
+        self.hide_before.map_or(0, |line| {
+            line.saturating_sub(self.trimmed).min(self.lines.len())
+        })
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.scroll_offset < self.lines.len().saturating_sub(1) {
+            self.scroll_offset += 1;
+            self.is_scrolled = true;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        if self.scroll_offset > self.first_shown() {
+            self.scroll_offset -= 1;
+            self.is_scrolled = true;
+        }
+    }
+
+    /// Jump to the oldest line shown
+    pub fn home(&mut self) {
+        self.scroll_offset = self.first_shown();
+        self.is_scrolled = true;
+    }
+
+    /// Reset to auto-scrolling
+    pub fn follow(&mut self) {
+        self.is_scrolled = false;
+    }
+
+    /// Scroll just far enough that line `index` is visible.
+    pub fn reveal(&mut self, index: usize) {
+        if index < self.scroll_offset {
+            self.scroll_offset = index;
+        } else if self.height > 0 && index >= self.scroll_offset + self.height {
+            self.scroll_offset = index + 1 - self.height;
+        }
+        self.is_scrolled = true;
+    }
+
+    /// Scroll so line `index` is in the middle of the view.
+    pub fn center(&mut self, index: usize) {
+        self.scroll_offset = index.saturating_sub(self.height / 2);
+        self.is_scrolled = true;
+    }
+
+    /// Whether the view tracks the newest line.
+    pub fn is_following(&self) -> bool {
+        !self.is_scrolled && self.frozen.is_none()
+    }
+
+    /// Auto-scroll to the latest entry if not manually scrolled or frozen.
+    pub fn auto_scroll(&mut self, height: usize) {
+        self.height = height;
+        if self.is_following() {
+            self.scroll_offset = self.lines.len().saturating_sub(height);
+        }
+        self.scroll_offset = self.scroll_offset.max(self.first_shown());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::line::{Message, MessageSender};
+use std::io::{self, Write};
+use std::mem;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Lines that may wait for a slow command before new ones are dropped.
+const QUEUE_LINES: usize = 1000;
+
+/// How long the command gets to exit after its input is closed on quit.
+const EXIT_GRACE: Duration = Duration::from_secs(1);
+
+/// A shell command that received lines are written to, one per line.
+///
+/// Lines are written from a thread of their own, so a command that reads
+/// slowly never holds up the display; once it is `QUEUE_LINES` behind, new
+/// lines are dropped for it instead.
+pub struct Pipe {
+    command: String,
+    child: Child,
+    lines: Option<SyncSender<String>>, // `None` once the command has gone
+    writer: Option<JoinHandle<()>>,
+    dropped: usize, // Lines dropped in the current backlog
+}
+
+impl Pipe {
+    /// Start `command` in the shell, with its output discarded so it cannot
+    /// draw over the UI. Reports through `tx` if it stops taking lines.
+    pub fn spawn(command: &str, tx: MessageSender) -> io::Result<Self> {
+        let mut child = shell(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let (lines, queue) = mpsc::sync_channel(QUEUE_LINES);
+        let name = command.to_string();
+        let writer = thread::spawn(move || write_lines(stdin, queue, &name, &tx));
+        Ok(Pipe {
+            command: command.to_string(),
+            child,
+            lines: Some(lines),
+            writer: Some(writer),
+            dropped: 0,
+        })
+    }
+
+    /// Queue `line` for the command. Returns a warning to show when lines
+    /// start being dropped, and again once the command catches up.
+    pub fn send(&mut self, line: String) -> Option<String> {
+        let lines = self.lines.as_ref()?;
+        match lines.try_send(line) {
+            Ok(()) if self.dropped > 0 => {
+                let dropped = mem::take(&mut self.dropped);
+                Some(format!(
+                    "Pipe command caught up; {} line{} not passed to it",
+                    dropped,
+                    if dropped == 1 { " was" } else { "s were" }
+                ))
+            }
+            Ok(()) => None,
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                (self.dropped == 1).then(|| {
+                    format!(
+                        "Pipe command {:?} is falling behind; dropping lines",
+                        self.command
+                    )
+                })
+            }
+            // The writer has already reported why
+            Err(TrySendError::Disconnected(_)) => {
+                self.lines = None;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for Pipe {
+    // Close the command's input so it can finish, killing it if it has not
+    // exited shortly after
+    fn drop(&mut self) {
+        self.lines = None;
+        let deadline = Instant::now() + EXIT_GRACE;
+        while Instant::now() < deadline {
+            if exited(&mut self.child) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Also unblocks a writer stuck on a command that stopped reading.
+        // The child is only reaped after this, so its group is still its own
+        kill(&mut self.child);
+        let _ = self.child.wait();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Write queued lines to the command until the queue closes, or report
+/// through `tx` when the command stops taking them.
+fn write_lines(mut stdin: ChildStdin, queue: Receiver<String>, command: &str, tx: &MessageSender) {
+    for line in queue {
+        let written = stdin
+            .write_all(line.as_bytes())
+            .and_then(|_| stdin.write_all(b"\n"))
+            .and_then(|_| stdin.flush());
+        if let Err(e) = written {
+            let _ = tx.send(Message::Error(format!(
+                "Pipe command {:?} stopped taking lines: {}",
+                command, e
+            )));
+            return;
+        }
+    }
+}
+
+/// The command in a process group of its own, so that whatever it starts
+/// can be killed with it.
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Whether the command has exited. It is left unreaped, so its process
+/// group cannot be handed to another before `kill` signals it.
+#[cfg(unix)]
+fn exited(child: &mut Child) -> bool {
+    // SAFETY: `info` is plain data for waitid to fill in, and WNOWAIT leaves
+    // the child for `Child::wait` to reap. si_pid stays 0 if it is running.
+    unsafe {
+        let mut info: libc::siginfo_t = mem::zeroed();
+        libc::waitid(
+            libc::P_PID,
+            child.id() as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        ) == 0
+            && info.si_pid() != 0
+    }
+}
+
+#[cfg(windows)]
+fn exited(child: &mut Child) -> bool {
+    matches!(child.try_wait(), Ok(Some(_)))
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // SAFETY: kill has no memory safety requirements. The group is the one
+    // `shell` created for the child, which `exited` never reaps, so the
+    // group id cannot have been reused before this.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
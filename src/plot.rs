@@ -0,0 +1,62 @@
+use regex::Regex;
+use std::collections::VecDeque;
+
+/// Rows the plot pane takes up, borders included.
+pub const PLOT_HEIGHT: u16 = 10;
+
+/// Recent values of one metric pulled out of received lines.
+pub struct Plot {
+    regex: Regex,
+    window: usize,
+    pub values: VecDeque<f64>,
+}
+
+impl Plot {
+    /// Plot the first capture group of `pattern`, keeping `window` values.
+    pub fn new(pattern: &str, window: usize) -> Result<Self, String> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| format!("Invalid plot pattern {:?}: {}", pattern, e))?;
+        if regex.captures_len() < 2 {
+            return Err(format!(
+                "Plot pattern {:?} needs a capture group around the value",
+                pattern
+            ));
+        }
+        Ok(Plot {
+            regex,
+            window: window.max(2),
+            values: VecDeque::with_capacity(window),
+        })
+    }
+
+    /// Record the value in `line`, if it has one.
+    pub fn observe(&mut self, line: &str) {
+        let Some(value) = self
+            .regex
+            .captures(line)
+            .and_then(|captures| captures.get(1))
+            .and_then(|value| value.as_str().trim().parse::<f64>().ok())
+            .filter(|value| value.is_finite())
+        else {
+            return;
+        };
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Smallest and largest value in the window.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        let first = *self.values.front()?;
+        Some(
+            self.values
+                .iter()
+                .fold((first, first), |(min, max), &v| (min.min(v), max.max(v))),
+        )
+    }
+}
@@ -0,0 +1,91 @@
+use crate::baud;
+use crate::line::{Message, MessageSender};
+use serialport::SerialPortBuilder;
+use std::io;
+use std::time::Duration;
+
+/// Most bytes `--read-buffer` lets a single read take from a port.
+pub const MAX_READ_BUFFER: u32 = 1024 * 1024;
+
+/// Everything needed to (re)open one port.
+#[derive(Clone, Debug)]
+pub struct PortSpec {
+    pub builder: SerialPortBuilder,
+    pub baud_rate: u32,
+    pub custom_baud: bool,  // Set rates the driver rejects with termios2
+    pub read_buffer: usize, // Most bytes taken from the port per read
+    pub silence_limit: Option<Duration>, // Reopen the port after this long without data
+    pub source: Option<PortSource>, // Where it stands when several ports are read
+}
+
+/// A port's place among those read at once with `--extra-port`.
+#[derive(Clone, Debug)]
+pub struct PortSource {
+    pub index: usize, // 0 for the port lines are sent to
+    pub tag: String,
+}
+
+impl PortSpec {
+    /// Open the port for blocking reads.
+    #[cfg(not(feature = "async"))]
+    pub fn open(&self) -> serialport::Result<Box<dyn serialport::SerialPort>> {
+        match self.builder.clone().open() {
+            Err(e) if self.wants_custom(&e) => {
+                let port = self.fallback_builder().open_native()?;
+                baud::set_custom_rate(&port, self.baud_rate)?;
+                Ok(Box::new(port))
+            }
+            result => result,
+        }
+    }
+
+    /// Open the port as an async stream; must be called inside a Tokio runtime.
+    #[cfg(feature = "async")]
+    pub fn open_async(&self) -> serialport::Result<tokio_serial::SerialStream> {
+        use tokio_serial::SerialPortBuilderExt;
+        match self.builder.clone().open_native_async() {
+            Err(e) if self.wants_custom(&e) => {
+                let stream = self.fallback_builder().open_native_async()?;
+                baud::set_custom_rate(&stream, self.baud_rate)?;
+                Ok(stream)
+            }
+            result => result,
+        }
+    }
+
+    /// The error a read ends with once the port has been silent for
+    /// `silence_limit`, so the port is reopened as if it had failed.
+    pub fn silence_error(&self) -> io::Error {
+        let limit = self.silence_limit.unwrap_or_default();
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("no data for {:.1}s", limit.as_secs_f64()),
+        )
+    }
+
+    /// Tell the UI about a change in the port's link. The UI follows the
+    /// link of the port it sends to; the extra ports report losing theirs as
+    /// an error and reconnect quietly.
+    pub fn report(&self, tx: &MessageSender, message: Message) -> Result<(), Message> {
+        let Some(source) = self.source.as_ref().filter(|source| source.index > 0) else {
+            return tx.send(message);
+        };
+        let error = match message {
+            Message::Disconnected(reason) => format!("Disconnected: {}", reason),
+            Message::GaveUp => "Giving up: could not reconnect to the port".to_string(),
+            _ => return Ok(()),
+        };
+        tx.send(Message::Error(format!("[{}] {}", source.tag, error)))
+    }
+
+    fn wants_custom(&self, err: &serialport::Error) -> bool {
+        self.custom_baud && baud::CUSTOM_RATES && baud::is_rate_rejected(err)
+    }
+
+    /// Builder for opening at a standard rate before switching to the real one.
+    fn fallback_builder(&self) -> SerialPortBuilder {
+        self.builder
+            .clone()
+            .baud_rate(baud::nearest_standard(self.baud_rate))
+    }
+}
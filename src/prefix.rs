@@ -0,0 +1,34 @@
+use crate::config::StripConfig;
+use regex::Regex;
+
+/// A tag removed from the start of received lines before they are shown.
+pub enum Prefix {
+    Literal(String),
+    /// Anchored to the start of the line.
+    Pattern(Regex),
+}
+
+impl Prefix {
+    /// The prefix set in the `[strip]` config section, if any.
+    pub fn from_config(config: &StripConfig) -> Result<Option<Self>, String> {
+        match (&config.prefix, &config.pattern) {
+            (Some(_), Some(_)) => Err("Set either strip.prefix or strip.pattern, not both".into()),
+            (Some(prefix), None) => Ok(Some(Prefix::Literal(prefix.clone()))),
+            (None, Some(pattern)) => Regex::new(&format!("^(?:{})", pattern))
+                .map(|regex| Some(Prefix::Pattern(regex)))
+                .map_err(|e| format!("Invalid strip pattern {:?}: {}", pattern, e)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// `line` without the prefix, or unchanged if it does not start with it.
+    pub fn strip<'a>(&self, line: &'a str) -> &'a str {
+        match self {
+            Prefix::Literal(prefix) => line.strip_prefix(prefix.as_str()).unwrap_or(line),
+            Prefix::Pattern(regex) => match regex.find(line) {
+                Some(found) => &line[found.end()..],
+                None => line,
+            },
+        }
+    }
+}
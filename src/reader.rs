@@ -0,0 +1,136 @@
+use crate::line::{LineSplitter, Message, MessageSender, ReaderControl, PAUSE_POLL};
+use crate::port::PortSpec;
+use crate::reconnect::Reconnect;
+use crate::session::{self, Recorder};
+use serialport::SerialPort;
+use std::io;
+use std::thread;
+use std::time::Instant;
+
+/// Spawn a thread that reads from the serial port and sends complete lines
+/// through `tx`, reopening the port from `spec` if it fails.
+pub fn spawn(
+    port: Box<dyn SerialPort>,
+    spec: PortSpec,
+    reconnect: Reconnect,
+    control: ReaderControl,
+    recorder: Option<Recorder>,
+    tx: MessageSender,
+) {
+    thread::spawn(move || {
+        let mut port = port;
+        let mut recorder = recorder;
+        loop {
+            let read = read_lines(port.as_mut(), &spec, &control, &mut recorder, &tx);
+            let Err(e) = read else {
+                // The UI has gone away
+                return;
+            };
+            if let Some(diagnostics) = control.diagnostics() {
+                diagnostics.disconnected(&e.to_string());
+            }
+            if spec
+                .report(&tx, Message::Disconnected(e.to_string()))
+                .is_err()
+            {
+                return;
+            }
+            match reopen(&spec, reconnect, &control, &tx) {
+                Some(reopened) => port = reopened,
+                None => {
+                    let _ = spec.report(&tx, Message::GaveUp);
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Read lines, up to `spec.read_buffer` bytes at a time, until the port
+/// fails or stays silent too long (`Err`) or the channel closes (`Ok`).
+///
+/// Each read waits up to the port's timeout, so the loop wakes at most that
+/// often while nothing arrives.
+fn read_lines(
+    port: &mut dyn SerialPort,
+    spec: &PortSpec,
+    control: &ReaderControl,
+    recorder: &mut Option<Recorder>,
+    tx: &MessageSender,
+) -> io::Result<()> {
+    let mut buffer = vec![0; spec.read_buffer];
+    let mut splitter = LineSplitter::new(control.clone()).with_source(spec.source.as_ref());
+    let mut last_data = Instant::now();
+    loop {
+        if control.is_paused() {
+            // Leave the data in the port until reading resumes
+            thread::sleep(PAUSE_POLL);
+            // Time spent paused is not silence
+            last_data = Instant::now();
+            continue;
+        }
+        match port.read(&mut buffer) {
+            Ok(bytes_read) => {
+                if bytes_read > 0 {
+                    last_data = Instant::now();
+                }
+                if let Some(diagnostics) = control.diagnostics() {
+                    diagnostics.read(bytes_read);
+                }
+                session::record(recorder, &buffer[..bytes_read], tx);
+                for message in splitter.feed(&buffer[..bytes_read]) {
+                    if tx.send(message).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
+                // Nothing arrived in time, which is not an error
+                if let Some(diagnostics) = control.diagnostics() {
+                    diagnostics.timeout();
+                }
+                if spec
+                    .silence_limit
+                    .is_some_and(|limit| last_data.elapsed() >= limit)
+                {
+                    return Err(spec.silence_error());
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Try to reopen the port as often as `reconnect` allows, handing the UI a
+/// fresh writer on success.
+fn reopen(
+    spec: &PortSpec,
+    reconnect: Reconnect,
+    control: &ReaderControl,
+    tx: &MessageSender,
+) -> Option<Box<dyn SerialPort>> {
+    let mut attempt = 1;
+    while reconnect.allows(attempt) {
+        thread::sleep(reconnect.delay_for(attempt));
+        if let Some(diagnostics) = control.diagnostics() {
+            diagnostics.reconnecting();
+        }
+        let status = Message::Reconnecting {
+            attempt,
+            max: reconnect.attempts,
+        };
+        if spec.report(tx, status).is_err() {
+            return None;
+        }
+        if let Ok(port) = spec.open() {
+            if let Ok(writer) = port.try_clone() {
+                if spec.report(tx, Message::Reconnected(writer)).is_err() {
+                    return None;
+                }
+                return Some(port);
+            }
+        }
+        attempt += 1;
+    }
+    None
+}
@@ -0,0 +1,170 @@
+use crate::line::{LineSplitter, Message, MessageSender, ReaderControl, PAUSE_POLL};
+use crate::port::PortSpec;
+use crate::reconnect::Reconnect;
+use crate::session::{self, Recorder};
+use std::io;
+use std::thread;
+use tokio::io::AsyncReadExt;
+use tokio_serial::{SerialPort, SerialStream};
+
+/// Blocking handle to a port opened by [`spawn`], used for sending.
+pub type Writer = Box<dyn SerialPort>;
+
+/// Open every port in `specs` as an async stream on a background Tokio
+/// runtime and forward their lines through `tx`, reopening ports that fail.
+///
+/// Returns a blocking handle per port, in the same order as `specs`, for
+/// sending, or the index of the port that could not be opened and why. Only
+/// the first port is recorded.
+pub fn spawn(
+    specs: Vec<PortSpec>,
+    reconnect: Reconnect,
+    control: ReaderControl,
+    recorder: Option<Recorder>,
+    tx: MessageSender,
+) -> Result<Vec<Writer>, (usize, serialport::Error)> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .map_err(|e| (0, e.into()))?;
+
+    // Streams register with the runtime's reactor, so open them inside it
+    let mut writers = Vec::with_capacity(specs.len());
+    let mut tasks = Vec::with_capacity(specs.len());
+    {
+        let _guard = runtime.enter();
+        for (index, spec) in specs.into_iter().enumerate() {
+            let stream = spec.open_async().map_err(|e| (index, e))?;
+            writers.push(stream.try_clone().map_err(|e| (index, e))?);
+            tasks.push(runtime.spawn(run(
+                stream,
+                spec,
+                reconnect,
+                control.clone(),
+                recorder.clone().filter(|_| index == 0),
+                tx.clone(),
+            )));
+        }
+    }
+
+    thread::spawn(move || {
+        runtime.block_on(async {
+            for task in tasks {
+                let _ = task.await;
+            }
+        })
+    });
+
+    Ok(writers)
+}
+
+/// Read one port until the UI goes away or reconnecting gives up.
+async fn run(
+    stream: SerialStream,
+    spec: PortSpec,
+    reconnect: Reconnect,
+    control: ReaderControl,
+    recorder: Option<Recorder>,
+    tx: MessageSender,
+) {
+    let mut stream = stream;
+    let mut recorder = recorder;
+    loop {
+        let read = read_lines(stream, &spec, &control, &mut recorder, &tx).await;
+        let Err(e) = read else {
+            return;
+        };
+        if let Some(diagnostics) = control.diagnostics() {
+            diagnostics.disconnected(&e.to_string());
+        }
+        if spec
+            .report(&tx, Message::Disconnected(e.to_string()))
+            .is_err()
+        {
+            return;
+        }
+        match reopen(&spec, reconnect, &control, &tx).await {
+            Some(reopened) => stream = reopened,
+            None => {
+                let _ = spec.report(&tx, Message::GaveUp);
+                return;
+            }
+        }
+    }
+}
+
+/// Read lines, up to `spec.read_buffer` bytes at a time, until the port
+/// fails or stays silent too long (`Err`) or the channel closes (`Ok`).
+async fn read_lines(
+    mut stream: SerialStream,
+    spec: &PortSpec,
+    control: &ReaderControl,
+    recorder: &mut Option<Recorder>,
+    tx: &MessageSender,
+) -> io::Result<()> {
+    let mut buffer = vec![0; spec.read_buffer];
+    let mut splitter = LineSplitter::new(control.clone()).with_source(spec.source.as_ref());
+    loop {
+        if control.is_paused() {
+            // Leave the data in the port until reading resumes
+            tokio::time::sleep(PAUSE_POLL).await;
+            continue;
+        }
+        // Reads wait for data without a timeout, so silence is timed here
+        let read = match spec.silence_limit {
+            Some(limit) => match tokio::time::timeout(limit, stream.read(&mut buffer)).await {
+                Ok(read) => read,
+                Err(_) => return Err(spec.silence_error()),
+            },
+            None => stream.read(&mut buffer).await,
+        };
+        match read {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(bytes_read) => {
+                if let Some(diagnostics) = control.diagnostics() {
+                    diagnostics.read(bytes_read);
+                }
+                session::record(recorder, &buffer[..bytes_read], tx);
+                for message in splitter.feed(&buffer[..bytes_read]) {
+                    if tx.send(message).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn reopen(
+    spec: &PortSpec,
+    reconnect: Reconnect,
+    control: &ReaderControl,
+    tx: &MessageSender,
+) -> Option<SerialStream> {
+    let mut attempt = 1;
+    while reconnect.allows(attempt) {
+        tokio::time::sleep(reconnect.delay_for(attempt)).await;
+        if let Some(diagnostics) = control.diagnostics() {
+            diagnostics.reconnecting();
+        }
+        let status = Message::Reconnecting {
+            attempt,
+            max: reconnect.attempts,
+        };
+        if spec.report(tx, status).is_err() {
+            return None;
+        }
+        if let Ok(stream) = spec.open_async() {
+            if let Ok(writer) = stream.try_clone() {
+                if spec.report(tx, Message::Reconnected(writer)).is_err() {
+                    return None;
+                }
+                return Some(stream);
+            }
+        }
+        attempt += 1;
+    }
+    None
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+/// Longest wait between attempts when backing off.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How persistently to reopen a port that disappeared.
+#[derive(Clone, Copy, Debug)]
+pub struct Reconnect {
+    pub attempts: u32, // 0 retries forever
+    pub delay: Duration,
+    pub backoff: bool, // Double the delay after every failed attempt
+}
+
+impl Reconnect {
+    /// Whether attempt number `attempt` (1-based) may be made.
+    pub fn allows(&self, attempt: u32) -> bool {
+        self.attempts == 0 || attempt <= self.attempts
+    }
+
+    /// How long to wait before attempt number `attempt` (1-based).
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        if !self.backoff {
+            return self.delay;
+        }
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.delay
+            .saturating_mul(factor)
+            .min(MAX_DELAY.max(self.delay))
+    }
+}
@@ -0,0 +1,63 @@
+use crate::options::parse_duration;
+use serialport::SerialPort;
+use std::thread;
+use std::time::Duration;
+
+/// Sequence used by `--reset-on-connect` without a value: hold the ESP32
+/// enable line low through RTS for one pulse, with DTR released so the board
+/// boots normally. On Arduino-style boards the DTR edge resets too.
+pub const DEFAULT_SEQUENCE: &str = "dtr=0,rts=1,wait,rts=0";
+
+/// One step of a reset sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Dtr(bool),
+    Rts(bool),
+    Wait(Duration),
+}
+
+/// Modem control line changes that reset a board.
+#[derive(Clone, Debug)]
+pub struct ResetSequence {
+    steps: Vec<Step>,
+}
+
+impl ResetSequence {
+    /// Parse comma-separated steps: `dtr=1`, `dtr=0`, `rts=1` and `rts=0`
+    /// assert or release a line, `wait` pauses for `pulse`, and
+    /// `wait=<duration>` for that long instead.
+    pub fn parse(text: &str, pulse: Duration) -> Result<Self, String> {
+        let steps = text
+            .split(',')
+            .map(|step| {
+                let step = step.trim();
+                let (name, value) = step.split_once('=').unwrap_or((step, ""));
+                match (name.to_ascii_lowercase().as_str(), value) {
+                    ("dtr", "1") => Ok(Step::Dtr(true)),
+                    ("dtr", "0") => Ok(Step::Dtr(false)),
+                    ("rts", "1") => Ok(Step::Rts(true)),
+                    ("rts", "0") => Ok(Step::Rts(false)),
+                    ("wait", "") => Ok(Step::Wait(pulse)),
+                    ("wait", duration) => parse_duration(duration).map(Step::Wait),
+                    _ => Err(format!(
+                        "Unknown reset step {:?}, expected dtr=0|1, rts=0|1 or wait[=DURATION]",
+                        step
+                    )),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ResetSequence { steps })
+    }
+
+    /// Carry out the steps on `port`.
+    pub fn run(&self, port: &mut dyn SerialPort) -> serialport::Result<()> {
+        for &step in &self.steps {
+            match step {
+                Step::Dtr(level) => port.write_data_terminal_ready(level)?,
+                Step::Rts(level) => port.write_request_to_send(level)?,
+                Step::Wait(duration) => thread::sleep(duration),
+            }
+        }
+        Ok(())
+    }
+}
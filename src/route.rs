@@ -0,0 +1,31 @@
+use crate::config::RouteConfig;
+use crate::pane::Pane;
+use regex::Regex;
+
+/// An extra pane taking the received lines that match its pattern, which
+/// would otherwise go to the main pane.
+pub struct Route {
+    pub title: String,
+    regex: Regex,
+    pub pane: Pane,
+}
+
+impl Route {
+    pub fn from_config(config: &RouteConfig) -> Result<Self, String> {
+        let regex = Regex::new(&config.pattern).map_err(|e| {
+            format!(
+                "Invalid pattern {:?} for pane {:?}: {}",
+                config.pattern, config.name, e
+            )
+        })?;
+        Ok(Route {
+            title: config.name.clone(),
+            regex,
+            pane: Pane::new(),
+        })
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        self.regex.is_match(line)
+    }
+}
@@ -0,0 +1,85 @@
+use crate::classify::Classifier;
+use crate::config::{self, ClassifyConfig, Config, HighlightConfig, PanesConfig, Precedence};
+use crate::highlight::Highlight;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// The highlight and classify settings of `config.toml`, which can be saved
+/// under a name and swapped in at runtime.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    pub highlight: Vec<HighlightConfig>,
+    pub panes: PanesConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classify: Option<Vec<ClassifyConfig>>,
+    pub classify_precedence: Precedence,
+    pub loose_keywords: bool,
+}
+
+/// A rule set ready for use.
+pub struct CompiledRules {
+    pub main: Vec<Highlight>,
+    pub errors: Vec<Highlight>,
+    pub classifier: Classifier,
+}
+
+impl RuleSet {
+    /// The rules in `config`.
+    pub fn from_config(config: &Config) -> Self {
+        RuleSet {
+            highlight: config.highlight.clone(),
+            panes: config.panes.clone(),
+            classify: config.classify.clone(),
+            classify_precedence: config.classify_precedence,
+            loose_keywords: config.loose_keywords,
+        }
+    }
+
+    pub fn compile(&self) -> Result<CompiledRules, String> {
+        // Each pane falls back to the shared highlight rules
+        let highlights = |pane: &Option<Vec<HighlightConfig>>| {
+            Highlight::compile_all(pane.as_ref().unwrap_or(&self.highlight))
+        };
+        Ok(CompiledRules {
+            main: highlights(&self.panes.main.highlight)?,
+            errors: highlights(&self.panes.errors.highlight)?,
+            classifier: Classifier::compile(
+                self.classify.as_deref(),
+                self.classify_precedence,
+                self.loose_keywords,
+            )?,
+        })
+    }
+
+    /// Load the rule set saved as `name`.
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = path(name)?;
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid rule set {}: {}", path.display(), e))
+    }
+
+    /// Save under `name`, returning where the file went.
+    pub fn save(&self, name: &str) -> Result<PathBuf, String> {
+        let path = path(name)?;
+        let text = toml::to_string(self).map_err(|e| format!("Failed to save rules: {}", e))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+/// `rules/NAME.toml` in the config directory.
+fn path(name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Not a rule set name: {:?}", name));
+    }
+    let dir = config::config_dir().ok_or("No config directory to keep rule sets in")?;
+    Ok(dir.join("rules").join(format!("{}.toml", name)))
+}
@@ -0,0 +1,178 @@
+use crate::options::parse_duration;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// One step of an init script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Step {
+    Send(String),
+    Delay(Duration),
+}
+
+/// Shortest pause between sends kept in a recorded macro.
+const MIN_RECORDED_DELAY: Duration = Duration::from_millis(100);
+
+/// Commands sent in turn: an init script sent once after connecting, read
+/// from a file with one command per line, or a recorded macro. `@delay
+/// 500ms` pauses, and in a file blank lines and lines starting with `#` are
+/// skipped. With `--prompt` each command also waits for the prompt the one
+/// before it brings back.
+#[derive(Debug)]
+pub struct Script {
+    pub name: String, // What is running, e.g. `init script boot.txt`
+    steps: VecDeque<Step>,
+    resume_at: Option<Instant>,       // End of the current delay
+    prompt_timeout: Option<Duration>, // Longest wait for the prompt after a command
+    waiting: Option<Instant>,         // When the command awaiting its prompt was sent
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read init script {}: {}", path.display(), e))?;
+        let steps = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| match line.trim().strip_prefix("@delay") {
+                Some(duration) => parse_duration(duration.trim())
+                    .map(Step::Delay)
+                    .map_err(|e| format!("{}:{}: {}", path.display(), index + 1, e)),
+                None => Ok(Step::Send(line.trim_end().to_string())),
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Script {
+            name: format!("init script {}", path.display()),
+            steps,
+            resume_at: None,
+            prompt_timeout: None,
+            waiting: None,
+        })
+    }
+
+    /// A recorded macro, whose lines are `@delay` steps or sent as they are,
+    /// blank or not.
+    pub fn from_macro(name: String, lines: &[String]) -> Self {
+        let steps = lines
+            .iter()
+            .map(|line| {
+                let delay = line
+                    .strip_prefix("@delay ")
+                    .and_then(|duration| parse_duration(duration).ok());
+                match delay {
+                    Some(delay) => Step::Delay(delay),
+                    None => Step::Send(line.clone()),
+                }
+            })
+            .collect();
+        Script {
+            name,
+            steps,
+            resume_at: None,
+            prompt_timeout: None,
+            waiting: None,
+        }
+    }
+
+    /// Hold each command until the device shows its prompt after the one
+    /// before, waiting up to `timeout`.
+    pub fn wait_for_prompts(&mut self, timeout: Duration) {
+        self.prompt_timeout = Some(timeout);
+    }
+
+    pub fn saw_prompt(&mut self) {
+        self.waiting = None;
+    }
+
+    /// Whether the prompt has failed to come back within the timeout.
+    pub fn prompt_overdue(&self) -> bool {
+        match (self.waiting, self.prompt_timeout) {
+            (Some(sent), Some(timeout)) => sent.elapsed() >= timeout,
+            _ => false,
+        }
+    }
+
+    /// The next command that is due, if any; delays are waited out across
+    /// calls so the UI keeps running.
+    pub fn next_command(&mut self) -> Option<String> {
+        if self.waiting.is_some() {
+            return None;
+        }
+        if let Some(resume_at) = self.resume_at {
+            if Instant::now() < resume_at {
+                return None;
+            }
+            self.resume_at = None;
+        }
+        match self.steps.pop_front()? {
+            Step::Send(command) => {
+                if self.prompt_timeout.is_some() {
+                    self.waiting = Some(Instant::now());
+                }
+                Some(command)
+            }
+            Step::Delay(delay) => {
+                self.resume_at = Some(Instant::now() + delay);
+                None
+            }
+        }
+    }
+
+    /// Whether every step has run.
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty() && self.resume_at.is_none()
+    }
+}
+
+/// Lines sent while recording a macro, with the pauses between them.
+#[derive(Debug)]
+pub struct Recording {
+    lines: Vec<String>,         // As stored: commands and `@delay` steps
+    delays: bool,               // Keep pauses; off when replays wait for the prompt
+    last_send: Option<Instant>, // When the last recorded line went out
+    wait: Option<Duration>,     // Pause before the line being typed
+}
+
+impl Recording {
+    pub fn new(delays: bool) -> Self {
+        Recording {
+            lines: Vec::new(),
+            delays,
+            last_send: None,
+            wait: None,
+        }
+    }
+
+    /// The send prompt opened. The time since the last send is the pause
+    /// to keep; the time spent typing is not.
+    pub fn prompt_opened(&mut self) {
+        self.wait = self.last_send.map(|sent| sent.elapsed());
+    }
+
+    pub fn sent(&mut self, line: &str) {
+        if let Some(wait) = self.wait.take().filter(|_| self.delays) {
+            // To the nearest step, so replays do not claim false precision
+            let step = MIN_RECORDED_DELAY.as_millis();
+            let millis = (wait.as_millis() + step / 2) / step * step;
+            if millis > 0 {
+                self.lines.push(format!("@delay {}ms", millis));
+            }
+        }
+        self.lines.push(line.to_string());
+        self.last_send = Some(Instant::now());
+    }
+
+    /// Number of lines sent so far.
+    pub fn sends(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| !line.starts_with("@delay "))
+            .count()
+    }
+
+    pub fn finish(self) -> Vec<String> {
+        self.lines
+    }
+}
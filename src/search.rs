@@ -0,0 +1,101 @@
+use crate::app::PaneId;
+use regex::{Regex, RegexBuilder};
+
+/// Which panes a search looks in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scope {
+    #[default]
+    Both,
+    Main,
+    Errors,
+}
+
+impl Scope {
+    pub fn next(self) -> Self {
+        match self {
+            Scope::Both => Scope::Main,
+            Scope::Main => Scope::Errors,
+            Scope::Errors => Scope::Both,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Scope::Both => "both",
+            Scope::Main => "main",
+            Scope::Errors => "errors",
+        }
+    }
+
+    pub fn includes(self, pane: PaneId) -> bool {
+        match self {
+            Scope::Both => true,
+            Scope::Main => pane == PaneId::Main,
+            Scope::Errors => matches!(pane, PaneId::ErrorWarn | PaneId::Warnings),
+        }
+    }
+
+    /// Panes in scope, in the order matches are stepped through, with
+    /// `routes` routed panes shown.
+    pub fn panes(self, routes: usize) -> Vec<PaneId> {
+        match self {
+            Scope::Both => {
+                let mut panes = vec![PaneId::Main];
+                panes.extend((0..routes).map(PaneId::Route));
+                panes.extend([PaneId::ErrorWarn, PaneId::Warnings]);
+                panes
+            }
+            Scope::Main => vec![PaneId::Main],
+            Scope::Errors => vec![PaneId::ErrorWarn, PaneId::Warnings],
+        }
+    }
+}
+
+/// Text being searched for in the panes.
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub query: String,
+    pub case_sensitive: bool,
+    pub scope: Scope,
+    regex: Regex,
+}
+
+impl Search {
+    /// Search for `query` literally in the panes of `scope`, ignoring case
+    /// unless `case_sensitive`.
+    pub fn new(query: &str, case_sensitive: bool, scope: Scope) -> Self {
+        let regex = RegexBuilder::new(&regex::escape(query))
+            .case_insensitive(!case_sensitive)
+            .build()
+            .expect("escaped query is valid");
+        Search {
+            query: query.to_string(),
+            case_sensitive,
+            scope,
+            regex,
+        }
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.regex.is_match(line)
+    }
+
+    /// Tag shown in the main pane title while a search is active.
+    pub fn label(&self) -> String {
+        format!(
+            " [/{} {} {}]",
+            self.query,
+            case_label(self.case_sensitive),
+            self.scope.label()
+        )
+    }
+}
+
+/// `Aa` when case matters, `aa` when it is ignored.
+pub fn case_label(case_sensitive: bool) -> &'static str {
+    if case_sensitive {
+        "Aa"
+    } else {
+        "aa"
+    }
+}
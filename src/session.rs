@@ -0,0 +1,199 @@
+use crate::line::{LineSplitter, Message, MessageSender, ReaderControl, PAUSE_POLL};
+use crate::port::MAX_READ_BUFFER;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// First bytes of a session file.
+const MAGIC: &[u8] = b"aserial-session 1\n";
+
+/// pcap link type for private use, which analysis tools show as raw bytes.
+const LINKTYPE_USER0: u32 = 147;
+
+/// Longest chunk a pcap record claims to hold.
+const SNAPLEN: u32 = 65535;
+
+/// Replay speeds `}` and `{` step through.
+pub const REPLAY_SPEEDS: &[f64] = &[0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+/// How a recording lays out its chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// For `--replay`. Each record is the offset in microseconds since
+    /// recording started (u64) and the chunk length (u32), both
+    /// little-endian, followed by the chunk.
+    Session,
+    /// A classic little-endian pcap file with microsecond wall clock
+    /// timestamps and one packet per chunk, for Wireshark and similar tools.
+    Pcap,
+}
+
+/// Writes every chunk read from the port, with when it arrived, to one or
+/// more recording files.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Mutex<Recording>>,
+}
+
+struct Recording {
+    files: Vec<(File, RecordFormat)>,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder {
+            inner: Arc::new(Mutex::new(Recording {
+                files: Vec::new(),
+                started: Instant::now(),
+            })),
+        }
+    }
+
+    /// Also record to a new file at `path`.
+    pub fn add(&self, path: &Path, format: RecordFormat) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        match format {
+            RecordFormat::Session => file.write_all(MAGIC)?,
+            RecordFormat::Pcap => {
+                let mut header = Vec::with_capacity(24);
+                header.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+                header.extend_from_slice(&2u16.to_le_bytes());
+                header.extend_from_slice(&4u16.to_le_bytes());
+                // Timestamps are UTC and their accuracy is not known
+                header.extend_from_slice(&[0; 8]);
+                header.extend_from_slice(&SNAPLEN.to_le_bytes());
+                header.extend_from_slice(&LINKTYPE_USER0.to_le_bytes());
+                file.write_all(&header)?;
+            }
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.files.push((file, format));
+        Ok(())
+    }
+
+    pub fn write_chunk(&self, chunk: &[u8]) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Recording { files, started } = &mut *inner;
+        let offset = started.elapsed().as_micros() as u64;
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        for (file, format) in files {
+            // One write per record, so a crash leaves at most the last one torn
+            let mut record = Vec::with_capacity(16 + chunk.len());
+            match format {
+                RecordFormat::Session => {
+                    record.extend_from_slice(&offset.to_le_bytes());
+                    record.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                    record.extend_from_slice(chunk);
+                }
+                RecordFormat::Pcap => {
+                    let kept = &chunk[..chunk.len().min(SNAPLEN as usize)];
+                    record.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+                    record.extend_from_slice(&now.subsec_micros().to_le_bytes());
+                    record.extend_from_slice(&(kept.len() as u32).to_le_bytes());
+                    record.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                    record.extend_from_slice(kept);
+                }
+            }
+            file.write_all(&record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Record `chunk` if recording, giving up with an error through `tx` if the
+/// file cannot be written.
+pub fn record(recorder: &mut Option<Recorder>, chunk: &[u8], tx: &MessageSender) {
+    let Some(writer) = recorder.as_ref() else {
+        return;
+    };
+    if let Err(e) = writer.write_chunk(chunk) {
+        *recorder = None;
+        let _ = tx.send(Message::Error(format!("Recording stopped: {}", e)));
+    }
+}
+
+/// Open a recorded session and spawn a thread that plays it back through
+/// `tx`, at the speed `control` gives, which may change as it plays.
+pub fn replay(path: PathBuf, control: ReaderControl, tx: MessageSender) -> io::Result<()> {
+    let mut file = BufReader::new(File::open(&path)?);
+    let mut magic = [0; MAGIC.len()];
+    if file.read_exact(&mut magic).is_err() || magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an aserial session file",
+        ));
+    }
+
+    thread::spawn(move || {
+        let mut played = Duration::ZERO; // How far into the recording playback is
+        let mut last = Instant::now();
+        // As they were since `last`
+        let mut speed = control.replay_speed().unwrap_or(1.0);
+        let mut paused = control.is_paused();
+        let mut splitter = LineSplitter::new(control.clone());
+        loop {
+            let (offset, chunk) = match read_record(&mut file) {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = tx.send(Message::Error(format!("Replay stopped: {}", e)));
+                    return;
+                }
+            };
+            // Wait in short steps, so a change of speed or a pause takes
+            // effect at once. Time spent paused does not count.
+            loop {
+                let now = Instant::now();
+                if !paused {
+                    played += now.duration_since(last).mul_f64(speed);
+                }
+                last = now;
+                speed = control.replay_speed().unwrap_or(1.0);
+                paused = control.is_paused();
+                match offset.checked_sub(played) {
+                    _ if paused => thread::sleep(PAUSE_POLL),
+                    Some(wait) if !wait.is_zero() => {
+                        thread::sleep(wait.div_f64(speed).min(PAUSE_POLL))
+                    }
+                    _ => break,
+                }
+            }
+            for message in splitter.feed(&chunk) {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = tx.send(Message::Ended);
+    });
+    Ok(())
+}
+
+/// Next `(offset, chunk)`, or `None` at the end of the file.
+fn read_record(file: &mut impl Read) -> io::Result<Option<(Duration, Vec<u8>)>> {
+    let mut header = [0; 12];
+    match file.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let (offset, length) = header.split_at(8);
+    let offset = u64::from_le_bytes(offset.try_into().expect("8 bytes"));
+    let length = u32::from_le_bytes(length.try_into().expect("4 bytes"));
+    // No read is longer, so a longer chunk means the file is damaged
+    if length > MAX_READ_BUFFER {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("chunk of {} bytes is longer than any read", length),
+        ));
+    }
+    let mut chunk = vec![0; length as usize];
+    file.read_exact(&mut chunk)?;
+    Ok(Some((Duration::from_micros(offset), chunk)))
+}
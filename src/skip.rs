@@ -0,0 +1,74 @@
+use crate::options::parse_duration;
+use std::time::{Duration, Instant};
+
+/// How much of the output right after connecting `--skip-initial` drops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipInitial {
+    Lines(usize),
+    Time(Duration),
+}
+
+/// `20` for a number of lines, or a duration such as `500ms` or `2s`.
+pub fn parse_skip(text: &str) -> Result<SkipInitial, String> {
+    if let Ok(lines) = text.parse() {
+        return Ok(SkipInitial::Lines(lines));
+    }
+    parse_duration(text).map(SkipInitial::Time)
+}
+
+/// Drops the lines received in the window after each connect.
+pub struct Skipper {
+    limit: SkipInitial,
+    connected: Instant,
+    skipped: usize, // Lines dropped in the current window
+    done: bool,     // The window has passed
+}
+
+impl Skipper {
+    pub fn new(limit: SkipInitial) -> Self {
+        Skipper {
+            limit,
+            connected: Instant::now(),
+            skipped: 0,
+            done: false,
+        }
+    }
+
+    /// Start a new window, after reconnecting.
+    pub fn restart(&mut self) {
+        *self = Skipper::new(self.limit);
+    }
+
+    /// Whether data arriving now is still inside the window.
+    pub fn skipping(&self) -> bool {
+        !self.done
+            && match self.limit {
+                SkipInitial::Lines(lines) => self.skipped < lines,
+                SkipInitial::Time(time) => self.connected.elapsed() < time,
+            }
+    }
+
+    /// Whether to drop a line that just arrived.
+    pub fn skip_line(&mut self) -> bool {
+        if self.skipping() {
+            self.skipped += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Once the window has passed, a note saying what it dropped, given once.
+    pub fn note(&mut self) -> Option<String> {
+        if self.done || self.skipping() {
+            return None;
+        }
+        self.done = true;
+        (self.skipped > 0).then(|| {
+            format!(
+                "Skipped {} line{} after connecting",
+                self.skipped,
+                if self.skipped == 1 { "" } else { "s" }
+            )
+        })
+    }
+}
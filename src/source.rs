@@ -0,0 +1,52 @@
+use crate::config::SourceConfig;
+use ratatui::style::Color;
+use std::str::FromStr;
+
+/// Colors the ports of multi-port mode get in turn, unless configured,
+/// leaving out those of errors, warnings and ordinary lines.
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::LightBlue,
+    Color::White,
+    Color::LightCyan,
+    Color::LightMagenta,
+];
+
+/// One of the ports open in multi-port mode, as its lines are tagged.
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub tag: String,
+    pub color: Color, // Of the tag shown before each line
+}
+
+impl Source {
+    /// Tags and colors for the ports `names`, in order: those of the
+    /// `[[source]]` entry naming the port, or `A`, `B`, ... and a color of
+    /// their own.
+    pub fn resolve(names: &[String], configs: &[SourceConfig]) -> Result<Vec<Self>, String> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let config = configs.iter().find(|config| config.port == *name);
+                let tag = match config.and_then(|config| config.tag.clone()) {
+                    Some(tag) => tag,
+                    None if i < 26 => char::from(b'A' + i as u8).to_string(),
+                    None => (i + 1).to_string(),
+                };
+                let color = match config.and_then(|config| config.color.as_ref()) {
+                    Some(color) => Color::from_str(color)
+                        .map_err(|_| format!("Invalid color {:?} for port {:?}", color, name))?,
+                    None => COLORS[i % COLORS.len()],
+                };
+                Ok(Source { tag, color })
+            })
+            .collect()
+    }
+
+    /// The tag as put before a line, e.g. `[A]`.
+    pub fn label(&self) -> String {
+        format!("[{}]", self.tag)
+    }
+}
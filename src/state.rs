@@ -0,0 +1,45 @@
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// What aserial remembers between runs, kept in `state.toml` next to the
+/// config file.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct State {
+    /// Port opened on the last successful connect.
+    pub port: Option<String>,
+    /// Baud rate in use when aserial last connected or quit.
+    pub baud: Option<u32>,
+    /// Recorded macros by slot, `1` to `9`, as script lines.
+    pub macros: BTreeMap<String, Vec<String>>,
+}
+
+impl State {
+    /// Load the saved state; a missing or unreadable file is an empty state.
+    pub fn load() -> Self {
+        path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join("state.toml"))
+}
@@ -0,0 +1,201 @@
+use crate::classify::{Class, Severity};
+use crate::config::StructuredConfig;
+use ratatui::style::Color;
+use std::collections::HashMap;
+
+/// Keys holding the level and the message unless configured otherwise.
+const LEVEL_KEY: &str = "level";
+const MESSAGE_KEY: &str = "msg";
+
+/// Widest a column grows; longer pairs overflow it rather than pushing every
+/// later line out.
+const MAX_COLUMN: usize = 40;
+
+/// Level of a structured line, from its level key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// The usual spellings of a level, ignoring case.
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "fatal" | "panic" | "crit" | "critical" | "alert" | "emerg" | "err" | "error" => {
+                Some(Level::Error)
+            }
+            "warn" | "warning" | "wrn" => Some(Level::Warning),
+            "info" | "inf" | "notice" => Some(Level::Info),
+            "debug" | "dbg" | "trace" | "verbose" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+
+    /// Classification for the line: errors and warnings go to their pane,
+    /// anything else stays in the main one whatever its message says.
+    pub fn class(self) -> Option<Class> {
+        let severity = match self {
+            Level::Error => Severity::Error,
+            Level::Warning => Severity::Warning,
+            Level::Info | Level::Debug => return None,
+        };
+        Some(Class {
+            severity,
+            color: severity.color(),
+        })
+    }
+
+    /// Colour in the main pane, dimmed for debug output.
+    pub fn color(self) -> Color {
+        match self {
+            Level::Debug => Color::DarkGray,
+            _ => Color::Green,
+        }
+    }
+}
+
+/// A word of a line: a `key=value` pair or anything else.
+enum Token<'a> {
+    Pair { key: &'a str, value: &'a str }, // Value as written, quotes included
+    Word,
+}
+
+/// Parses `key=value` lines such as `level=error msg="boom" code=5`, for
+/// the level and optionally to line the pairs up in columns.
+pub struct Structured {
+    level_key: String,
+    message_key: String,
+    columns: bool,
+    widths: HashMap<String, usize>, // Widest pair seen for each key
+}
+
+impl Structured {
+    pub fn new() -> Self {
+        Structured {
+            level_key: LEVEL_KEY.to_string(),
+            message_key: MESSAGE_KEY.to_string(),
+            columns: false,
+            widths: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &StructuredConfig) -> Result<Self, String> {
+        for key in [&config.level_key, &config.message_key]
+            .into_iter()
+            .flatten()
+        {
+            if key.is_empty() || !key.chars().all(is_key_char) {
+                return Err(format!("Invalid key {:?} in [structured]", key));
+            }
+        }
+        let defaults = Structured::new();
+        Ok(Structured {
+            level_key: config.level_key.clone().unwrap_or(defaults.level_key),
+            message_key: config.message_key.clone().unwrap_or(defaults.message_key),
+            columns: config.columns,
+            ..defaults
+        })
+    }
+
+    /// Level given by the line's level key, if it has one we recognize.
+    pub fn level(&self, line: &str) -> Option<Level> {
+        tokens(line).into_iter().find_map(|token| match token {
+            Token::Pair { key, value } if key == self.level_key => Level::parse(unquote(value)),
+            _ => None,
+        })
+    }
+
+    /// The line with its pairs in columns, level and message first, when
+    /// columns are on and the line is nothing but pairs.
+    ///
+    /// Columns are as wide as the widest pair seen so far for their key, so
+    /// they settle after the first few lines.
+    pub fn columns(&mut self, line: &str) -> Option<String> {
+        if !self.columns {
+            return None;
+        }
+        let mut pairs = Vec::new();
+        for token in tokens(line) {
+            match token {
+                Token::Pair { key, value } => pairs.push((key, format!("{}={}", key, value))),
+                Token::Word => return None,
+            }
+        }
+        if pairs.is_empty() {
+            return None;
+        }
+        let rank = |key: &str| {
+            if key == self.level_key {
+                0
+            } else if key == self.message_key {
+                1
+            } else {
+                2
+            }
+        };
+        // Stable, so the other keys keep their order
+        pairs.sort_by_key(|(key, _)| rank(key));
+
+        let mut shown = String::new();
+        for (key, text) in pairs {
+            let width = self.widths.entry(key.to_string()).or_default();
+            *width = (*width).max(text.chars().count()).min(MAX_COLUMN);
+            shown.push_str(&format!("{:width$}  ", text, width = *width));
+        }
+        Some(shown.trim_end().to_string())
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_.-".contains(c)
+}
+
+/// Split a line at spaces outside double quotes.
+fn tokens(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let key_len = rest.find(|c| !is_key_char(c)).unwrap_or(rest.len());
+        let end = if key_len > 0 && rest[key_len..].starts_with('=') {
+            key_len + 1 + value_len(&rest[key_len + 1..])
+        } else {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        };
+        let token = &rest[..end];
+        tokens.push(match token.split_once('=') {
+            Some((key, value)) if key.len() == key_len && key_len > 0 => Token::Pair { key, value },
+            _ => Token::Word,
+        });
+        rest = rest[end..].trim_start();
+    }
+    tokens
+}
+
+/// Length of a value: up to the closing quote of a quoted one, with `\"`
+/// escapes, otherwise up to the next space.
+fn value_len(text: &str) -> usize {
+    if !text.starts_with('"') {
+        return text.find(char::is_whitespace).unwrap_or(text.len());
+    }
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    // An unterminated quote takes the rest of the line
+    text.len()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
@@ -0,0 +1,60 @@
+use crate::config::SuccessConfig;
+use regex::{Regex, RegexSet};
+use std::time::Duration;
+
+/// How long the main pane's border stays green after a success line.
+pub const FLASH: Duration = Duration::from_secs(1);
+
+/// Received lines that mean the device got where it should, such as the end
+/// of its boot, from the `[success]` config section and `--exit-on`.
+#[derive(Debug)]
+pub struct Success {
+    patterns: RegexSet,     // Lines that flash the border, and more as set below
+    exit_on: Option<Regex>, // Lines that also quit, whatever `exit` says
+    pub bell: bool,         // Ring the terminal bell
+    pub pause: bool,        // Pause the display at the line
+    exit: bool,             // Quit on any of `patterns`
+}
+
+impl Success {
+    /// Only quitting on lines matching `exit_on`.
+    pub fn new(exit_on: Regex) -> Self {
+        Success {
+            patterns: RegexSet::empty(),
+            exit_on: Some(exit_on),
+            bell: false,
+            pause: false,
+            exit: false,
+        }
+    }
+
+    pub fn from_config(config: &SuccessConfig, exit_on: Option<Regex>) -> Result<Self, String> {
+        let patterns = RegexSet::new(&config.patterns)
+            .map_err(|e| format!("Invalid pattern in [success]: {}", e))?;
+        Ok(Success {
+            patterns,
+            exit_on,
+            bell: config.bell,
+            pause: config.pause,
+            exit: config.exit,
+        })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.patterns.is_match(line) || self.exits(line)
+    }
+
+    /// Whether some line would end the program.
+    pub fn quits(&self) -> bool {
+        self.exit_on.is_some() || (self.exit && !self.patterns.is_empty())
+    }
+
+    /// Whether `line` ends the program.
+    pub fn exits(&self, line: &str) -> bool {
+        (self.exit && self.patterns.is_match(line))
+            || self
+                .exit_on
+                .as_ref()
+                .is_some_and(|exit_on| exit_on.is_match(line))
+    }
+}
@@ -0,0 +1,80 @@
+use crate::config::NumericLevelsConfig;
+use crate::structured::Level;
+
+/// Highest syslog priority: facility 23, severity 7.
+const MAX_PRIORITY: u32 = 191;
+
+/// Reads the numeric level some firmwares and the Linux kernel put in front
+/// of a line, syslog style: `<3>` for an error, `<4>` for a warning, `<7>`
+/// for debug output.
+#[derive(Debug)]
+pub struct NumericLevels {
+    error: u8,   // Severities up to this one are errors
+    warning: u8, // Then warnings up to this one
+    debug: u8,   // Severities from this one on are debug output
+    strip: bool, // Show lines without their level
+}
+
+impl NumericLevels {
+    /// The syslog meanings: 0 to 3 errors, 4 warnings and 7 debug.
+    pub fn new() -> Self {
+        NumericLevels {
+            error: 3,
+            warning: 4,
+            debug: 7,
+            strip: false,
+        }
+    }
+
+    pub fn from_config(config: &NumericLevelsConfig) -> Result<Self, String> {
+        let defaults = NumericLevels::new();
+        let levels = NumericLevels {
+            error: config.error.unwrap_or(defaults.error),
+            warning: config.warning.unwrap_or(defaults.warning),
+            debug: config.debug.unwrap_or(defaults.debug),
+            strip: config.strip,
+        };
+        if !(levels.error < levels.warning && levels.warning < levels.debug && levels.debug <= 7) {
+            return Err(format!(
+                "Invalid [numeric_levels]: error ({}), warning ({}) and debug ({}) must be rising severities from 0 to 7",
+                levels.error, levels.warning, levels.debug
+            ));
+        }
+        Ok(levels)
+    }
+
+    /// Level given by the line's numeric prefix, if it has one.
+    pub fn level(&self, line: &str) -> Option<Level> {
+        let (severity, _) = prefix(line)?;
+        Some(if severity <= self.error {
+            Level::Error
+        } else if severity <= self.warning {
+            Level::Warning
+        } else if severity >= self.debug {
+            Level::Debug
+        } else {
+            Level::Info
+        })
+    }
+
+    /// The line as shown: without its numeric prefix when stripping.
+    pub fn strip<'a>(&self, line: &'a str) -> &'a str {
+        match prefix(line) {
+            Some((_, len)) if self.strip => &line[len..],
+            _ => line,
+        }
+    }
+}
+
+/// Severity and length of a leading `<N>`. A full syslog priority such as
+/// `<134>` also carries the facility, which is left out.
+fn prefix(line: &str) -> Option<(u8, usize)> {
+    let rest = line.strip_prefix('<')?;
+    let end = rest.find('>')?;
+    let digits = &rest[..end];
+    if digits.is_empty() || digits.len() > 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let priority: u32 = digits.parse().ok()?;
+    (priority <= MAX_PRIORITY).then_some(((priority % 8) as u8, end + 2))
+}
@@ -0,0 +1,358 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// How non-printable control characters are shown in the panes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlDisplay {
+    /// Pass control characters through to the terminal untouched.
+    Raw,
+    /// Caret notation, e.g. `^G` for BEL and `^[` for ESC.
+    Caret,
+    /// Unicode control pictures, e.g. `␇` for BEL and `␛` for ESC.
+    Pictures,
+}
+
+impl ControlDisplay {
+    /// Cycle Raw -> Caret -> Pictures -> Raw.
+    pub fn next(self) -> Self {
+        match self {
+            ControlDisplay::Raw => ControlDisplay::Caret,
+            ControlDisplay::Caret => ControlDisplay::Pictures,
+            ControlDisplay::Pictures => ControlDisplay::Raw,
+        }
+    }
+
+    /// Short tag shown in the pane title, empty for the default mode.
+    pub fn label(self) -> &'static str {
+        match self {
+            ControlDisplay::Raw => "",
+            ControlDisplay::Caret => " [ctrl: ^X]",
+            ControlDisplay::Pictures => " [ctrl: \u{2400}]",
+        }
+    }
+}
+
+/// How NUL bytes are shown, ahead of and whatever the `ControlDisplay`
+/// mode, as some terminals drop or stop at them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NulDisplay {
+    /// Leave NULs to the control character mode.
+    Raw,
+    /// The escape `\x00`.
+    Escape,
+    /// The control picture `␀`.
+    Glyph,
+}
+
+/// Replace the NULs in `line` according to `mode`.
+pub fn show_nul(line: &str, mode: NulDisplay) -> Cow<'_, str> {
+    match mode {
+        _ if !line.contains('\0') => Cow::Borrowed(line),
+        NulDisplay::Raw => Cow::Borrowed(line),
+        NulDisplay::Escape => Cow::Owned(line.replace('\0', "\\x00")),
+        NulDisplay::Glyph => Cow::Owned(line.replace('\0', "\u{2400}")),
+    }
+}
+
+/// Render the C0 control characters and DEL in `line` according to `mode`.
+pub fn show_control_chars(line: &str, mode: ControlDisplay) -> Cow<'_, str> {
+    if mode == ControlDisplay::Raw || !line.chars().any(is_control) {
+        return Cow::Borrowed(line);
+    }
+
+    let mut out = String::with_capacity(line.len() + 8);
+    for c in line.chars() {
+        if !is_control(c) {
+            out.push(c);
+            continue;
+        }
+        match mode {
+            ControlDisplay::Caret => {
+                // ^@ .. ^_ for 0x00..0x1F, ^? for DEL
+                out.push('^');
+                out.push(((c as u8) ^ 0x40) as char);
+            }
+            ControlDisplay::Pictures => {
+                // U+2400 .. U+241F mirror 0x00..0x1F, U+2421 is DEL
+                let picture = if c == '\x7f' {
+                    '\u{2421}'
+                } else {
+                    char::from_u32(0x2400 + c as u32).unwrap_or(c)
+                };
+                out.push(picture);
+            }
+            ControlDisplay::Raw => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn is_control(c: char) -> bool {
+    (c as u32) < 0x20 || c == '\x7f'
+}
+
+/// Replace each tab in `line` with spaces up to the next multiple of `width`
+/// columns.
+pub fn expand_tabs(line: &str, width: usize) -> Cow<'_, str> {
+    if !line.contains('\t') {
+        return Cow::Borrowed(line);
+    }
+    let mut out = String::with_capacity(line.len() + width);
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = width - column % width;
+            out.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            out.push(c);
+            column += 1;
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Split `line` at each `\n` escape, a backslash followed by `n` as JSON
+/// writes a newline inside a string, so a multiline payload sent as one
+/// line reads as several. A `\r\n` escape splits once, and an escaped
+/// backslash (`\\n`) does not split.
+pub fn split_escaped_newlines(line: &str) -> Vec<String> {
+    if !line.contains("\\n") {
+        return vec![line.to_string()];
+    }
+    let chars: Vec<char> = line.chars().collect();
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('\\', Some('n')) => pieces.push(std::mem::take(&mut current)),
+            ('\\', Some('r')) if chars.get(i + 2..i + 4) == Some(&['\\', 'n']) => {}
+            ('\\', Some(&next)) => {
+                current.push('\\');
+                current.push(next);
+            }
+            (c, _) => {
+                current.push(c);
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    pieces.push(current);
+    pieces
+}
+
+/// Remove ANSI escape sequences (CSI, OSC and two-byte escapes) from `line`.
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains('\x1b') {
+        return Cow::Borrowed(line);
+    }
+
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters and intermediates up to a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST (ESC \)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Any other escape is ESC plus one character
+            _ => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Bytes shown per row of a hex dump.
+pub const HEX_ROW: usize = 16;
+
+/// `hexdump -C` style rows: offset, bytes in hex, then the printable ASCII
+/// bytes with everything else as `.`.
+///
+/// A run of at least `collapse` identical bytes (0 = never) takes a single
+/// row such as `00 × 4096`.
+pub fn hex_dump(bytes: &[u8], collapse: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        let run = rest.iter().take_while(|&&b| b == rest[0]).count();
+        if collapse > 0 && run >= collapse {
+            rows.push(format!("{:08x}  {:02x} × {}", offset, rest[0], run));
+            offset += run;
+            continue;
+        }
+
+        let chunk = &rest[..rest.len().min(HEX_ROW)];
+        let mut hex = String::with_capacity(HEX_ROW * 3 + 1);
+        for (i, byte) in chunk.iter().enumerate() {
+            if i == HEX_ROW / 2 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02x} ", byte));
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        rows.push(format!(
+            "{:08x}  {:<width$} |{}|",
+            offset,
+            hex,
+            ascii,
+            width = HEX_ROW * 3 + 1
+        ));
+        offset += chunk.len();
+    }
+    rows
+}
+
+/// Shorten every run of at least `min` identical characters (0 = never) to
+/// one of them followed by `×N`, e.g. `\0×4096`.
+pub fn collapse_runs(line: &str, min: usize) -> Cow<'_, str> {
+    if min == 0 || line.len() < min {
+        return Cow::Borrowed(line);
+    }
+    let mut out = String::new();
+    let mut changed = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut run = 1;
+        while chars.peek() == Some(&c) {
+            chars.next();
+            run += 1;
+        }
+        if run >= min {
+            out.push(c);
+            out.push_str(&format!("×{}", run));
+            changed = true;
+        } else {
+            out.extend(std::iter::repeat_n(c, run));
+        }
+    }
+    if changed {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+/// How received bytes are turned into text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Decoding {
+    /// UTF-8, replacing invalid sequences with U+FFFD (`�`).
+    Lossy,
+    /// UTF-8, showing each invalid byte as a `\xNN` escape.
+    Escape,
+    /// ISO 8859-1: every byte is the code point of the same value.
+    Latin1,
+    /// IBM PC code page 437, as used by DOS-era equipment.
+    Cp437,
+}
+
+impl Decoding {
+    /// Inverse of `decoding as u8`.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Decoding::Escape,
+            2 => Decoding::Latin1,
+            3 => Decoding::Cp437,
+            _ => Decoding::Lossy,
+        }
+    }
+
+    /// Tag shown in the main pane title, empty for UTF-8.
+    pub fn label(self) -> &'static str {
+        match self {
+            Decoding::Lossy | Decoding::Escape => "",
+            Decoding::Latin1 => " [Latin-1]",
+            Decoding::Cp437 => " [CP437]",
+        }
+    }
+}
+
+/// Characters for CP437 bytes 0x80 to 0xFF; the rest are ASCII.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/// Decode one received line according to `decoding`.
+pub fn decode(bytes: &[u8], decoding: Decoding) -> Cow<'_, str> {
+    match decoding {
+        Decoding::Lossy => String::from_utf8_lossy(bytes),
+        Decoding::Escape => escape_invalid(bytes),
+        Decoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        Decoding::Cp437 => bytes
+            .iter()
+            .map(|&b| match b.checked_sub(0x80) {
+                Some(high) => CP437_HIGH.chars().nth(high as usize).unwrap_or('?'),
+                None => b as char,
+            })
+            .collect(),
+    }
+}
+
+/// Valid UTF-8 as is, with every invalid byte as `\xNN` so nothing is lost.
+fn escape_invalid(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(bytes.len() + 8);
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            let _ = write!(out, "\\x{:02X}", byte);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// What a terminal would show for a line redrawn with carriage returns: the
+/// last non-empty stretch after a `\r`.
+pub fn overwrite(line: &str) -> &str {
+    line.rsplit('\r')
+        .find(|part| !part.is_empty())
+        .unwrap_or("")
+}
+
+/// The percentage a line ends with, e.g. 42 for `Flashing... 42%`.
+pub fn trailing_percent(line: &str) -> Option<u16> {
+    let number = line.trim_end().strip_suffix('%')?.trim_end();
+    let start = number
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    number[start..]
+        .parse::<u16>()
+        .ok()
+        .filter(|&percent| percent <= 100)
+}
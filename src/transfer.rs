@@ -0,0 +1,248 @@
+use regex::Regex;
+use serialport::{ClearBuffer, SerialPort};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bytes written per call, so throttling and progress stay smooth.
+const CHUNK: usize = 64;
+/// How often the thread checks for a cancel while it waits.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// How to pace a file being sent.
+#[derive(Clone, Debug, Default)]
+pub struct Pacing {
+    pub line_delay: Duration,     // Pause after each line
+    pub byte_delay: Duration,     // Pause after each byte
+    pub prompt: Option<Regex>,    // Wait for the reader to see this prompt after each line
+    pub prompt_timeout: Duration, // Longest wait for the prompt before giving up on a file
+    pub rate: Option<u32>,        // Bytes per second, unlimited if `None`
+}
+
+/// How a transfer ended.
+pub enum Outcome {
+    Sent,
+    /// Stopped on request after this many bytes went out.
+    Cancelled(u64),
+    Failed(String),
+}
+
+/// What the sending thread reports back.
+enum Event {
+    Progress(u64),
+    Finished(Outcome),
+}
+
+/// Why the thread stopped sending early.
+enum Stop {
+    Cancelled,
+    Failed(String),
+}
+
+impl From<io::Error> for Stop {
+    fn from(e: io::Error) -> Self {
+        Stop::Failed(format!("Send failed: {}", e))
+    }
+}
+
+/// A file being sent to the port on a background thread.
+pub struct Transfer {
+    pub name: String,
+    pub total: u64,
+    pub sent: u64,
+    events: Receiver<Event>,
+    prompts: mpsc::Sender<()>, // Tells the thread the device printed its prompt
+    cancel: Arc<AtomicBool>,   // Asks the thread to stop
+}
+
+impl Transfer {
+    /// Read the file at `path` and start sending it through `writer`.
+    pub fn start(path: &Path, writer: Box<dyn SerialPort>, pacing: Pacing) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let (events_tx, events) = mpsc::channel();
+        let (prompts, prompts_rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let transfer = Transfer {
+            name: path.display().to_string(),
+            total: data.len() as u64,
+            sent: 0,
+            events,
+            prompts,
+            cancel: cancel.clone(),
+        };
+        thread::spawn(move || {
+            let mut sender = SendThread {
+                writer,
+                pacing,
+                events: events_tx,
+                prompts: prompts_rx,
+                cancel,
+                sent: 0,
+            };
+            let outcome = match sender.send(&data) {
+                Ok(()) => Outcome::Sent,
+                Err(Stop::Cancelled) => Outcome::Cancelled(sender.discard_unsent()),
+                Err(Stop::Failed(e)) => Outcome::Failed(e),
+            };
+            let _ = sender.events.send(Event::Finished(outcome));
+        });
+        Ok(transfer)
+    }
+
+    /// Stop sending as soon as the thread notices, within `CANCEL_POLL`.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Let the thread know the reader saw the device's prompt.
+    pub fn saw_prompt(&self) {
+        let _ = self.prompts.send(());
+    }
+
+    /// Catch up with the thread; `Some` once the transfer is over.
+    pub fn poll(&mut self) -> Option<Outcome> {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Progress(sent) => self.sent = sent,
+                Event::Finished(outcome) => return Some(outcome),
+            }
+        }
+        None
+    }
+
+    /// Progress shown in the bottom row.
+    pub fn label(&self) -> String {
+        if self.is_cancelling() {
+            return format!("Cancelling sending {}...", self.name);
+        }
+        let percent = (self.sent * 100).checked_div(self.total).unwrap_or(100);
+        format!(
+            "Sending {}: {}/{} bytes ({}%, Esc to cancel)",
+            self.name, self.sent, self.total, percent
+        )
+    }
+}
+
+/// The sending thread's side of a transfer.
+struct SendThread {
+    writer: Box<dyn SerialPort>,
+    pacing: Pacing,
+    events: mpsc::Sender<Event>,
+    prompts: Receiver<()>,
+    cancel: Arc<AtomicBool>,
+    sent: u64, // Bytes handed to the port so far
+}
+
+impl SendThread {
+    fn send(&mut self, data: &[u8]) -> Result<(), Stop> {
+        let started = Instant::now();
+        for line in data.split_inclusive(|&b| b == b'\n') {
+            // Only a prompt printed after this line counts
+            while self.prompts.try_recv().is_ok() {}
+
+            // A byte delay needs the bytes written one at a time
+            let chunk_size = if self.pacing.byte_delay.is_zero() {
+                CHUNK
+            } else {
+                1
+            };
+            for chunk in line.chunks(chunk_size) {
+                self.check_cancel()?;
+                write_chunk(self.writer.as_mut(), chunk)?;
+                self.sent += chunk.len() as u64;
+                if !self.pacing.byte_delay.is_zero() {
+                    // Drain first so the delay is between bytes on the wire
+                    self.writer.flush()?;
+                    self.pause(self.pacing.byte_delay)?;
+                }
+                if let Some(rate) = self.pacing.rate.filter(|&rate| rate > 0) {
+                    let due = Duration::from_secs_f64(self.sent as f64 / rate as f64);
+                    if let Some(wait) = due.checked_sub(started.elapsed()) {
+                        self.pause(wait)?;
+                    }
+                }
+                if self.events.send(Event::Progress(self.sent)).is_err() {
+                    // The UI has gone away
+                    return Ok(());
+                }
+            }
+            // Wait until the line has actually left the port
+            self.writer.flush()?;
+
+            if let Some(prompt) = &self.pacing.prompt {
+                let deadline = Instant::now() + self.pacing.prompt_timeout;
+                loop {
+                    self.check_cancel()?;
+                    match self.prompts.recv_timeout(CANCEL_POLL) {
+                        Ok(()) => break,
+                        Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => {}
+                        Err(RecvTimeoutError::Timeout) => {
+                            return Err(Stop::Failed(format!(
+                                "Send stopped: no prompt matching {:?} within {:?}",
+                                prompt.as_str(),
+                                self.pacing.prompt_timeout
+                            )))
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                    }
+                }
+            }
+            self.pause(self.pacing.line_delay)?;
+        }
+        Ok(())
+    }
+
+    fn check_cancel(&self) -> Result<(), Stop> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(Stop::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Sleep for `duration`, waking early to stop on a cancel.
+    fn pause(&self, duration: Duration) -> Result<(), Stop> {
+        let deadline = Instant::now() + duration;
+        loop {
+            self.check_cancel()?;
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                return Ok(());
+            };
+            thread::sleep(left.min(CANCEL_POLL));
+        }
+    }
+
+    /// Drop what is still queued in the driver, so nothing more reaches the
+    /// device, and return the bytes that did go out.
+    fn discard_unsent(&mut self) -> u64 {
+        let queued = self.writer.bytes_to_write().unwrap_or(0);
+        let _ = self.writer.clear(ClearBuffer::Output);
+        self.sent.saturating_sub(u64::from(queued))
+    }
+}
+
+/// Write all of `chunk`, waiting out a device that is not accepting data.
+fn write_chunk(writer: &mut dyn SerialPort, mut chunk: &[u8]) -> io::Result<()> {
+    while !chunk.is_empty() {
+        match writer.write(chunk) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => chunk = &chunk[written..],
+            // The output buffer is full; try again once it drains
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+                ) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,847 @@
+use crate::app::{App, Display, Inspection, PaneId, Selection, Timestamps};
+use crate::baud::BaudMenu;
+use crate::clock::WallTime;
+use crate::config::RouteLayout;
+use crate::diagnostics::DEBUG_HEIGHT;
+use crate::highlight::{self, ResponseMatch};
+use crate::keymap::Keymap;
+use crate::palette::Palette;
+use crate::pane::Pane;
+use crate::plot::{Plot, PLOT_HEIGHT};
+use crate::search::Search;
+use crate::source::Source;
+use crate::text;
+use ratatui::backend::Backend;
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Axis, Block, Borders, Chart, Clear, Dataset, Gauge, GraphType, Paragraph, Wrap,
+};
+use ratatui::Frame;
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Columns reserved for a timing delta such as `+0.003s`.
+const DELTA_WIDTH: usize = 8;
+/// Width of a timestamp, `HH:MM:SS.mmm`.
+const TIMESTAMP_WIDTH: usize = 12;
+
+/// Columns reserved for a line's length, such as `1024B`.
+const BYTE_COUNT_WIDTH: usize = 6;
+
+/// How far either side of a line its neighbours count towards its heat.
+const HEAT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Heat column colours, coolest first, each with the fewest lines within
+/// `HEAT_WINDOW` of a line that earn it.
+const HEAT_COLORS: [(usize, Color); 5] = [
+    (0, Color::DarkGray),
+    (3, Color::Blue),
+    (10, Color::Green),
+    (50, Color::Yellow),
+    (200, Color::Red),
+];
+
+/// Draw the UI, pinning each pane to its newest line unless it was scrolled.
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let mut area = f.size();
+    let (min_width, min_height) = app.min_size;
+    if area.width < min_width || area.height < min_height {
+        draw_too_small(f, area, app.min_size);
+        return;
+    }
+
+    // Reserve the bottom row for the prompt or notice while one is shown
+    let bottom_row = match (&app.prompt, &app.notice) {
+        (Some(prompt), _) => Some(format!("{}{}", app.prompt_label(prompt.kind), prompt.input)),
+        (None, Some(notice)) => Some(notice.clone()),
+        (None, None) => app.transfer.as_ref().map(|transfer| transfer.label()),
+    };
+    let line_age = app
+        .line_age
+        .as_ref()
+        .map(|age| Span::styled(age.label(), Style::default().fg(age.color())));
+    if bottom_row.is_some() || line_age.is_some() {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(area);
+        area = rows[0];
+        let mut row = rows[1];
+        // The age keeps the right end of the row, whatever else is shown
+        if let Some(line_age) = line_age {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [
+                        Constraint::Min(0),
+                        Constraint::Length(line_age.width() as u16 + 1),
+                    ]
+                    .as_ref(),
+                )
+                .split(row);
+            row = columns[0];
+            f.render_widget(
+                Paragraph::new(Line::from(line_age)).alignment(Alignment::Right),
+                columns[1],
+            );
+        }
+        f.render_widget(Paragraph::new(bottom_row.unwrap_or_default()), row);
+    }
+
+    let mut main_title = format!(
+        "{}{}{}{}{}{}{}{}{}{}{}{}{}{}",
+        app.main_title,
+        app.pause_label(),
+        app.lag_label(),
+        app.main.freeze_label(),
+        app.main.hidden_label(),
+        app.baud_label(),
+        app.traffic
+            .as_ref()
+            .map(|traffic| traffic.label())
+            .unwrap_or_default(),
+        app.signal_watch
+            .as_ref()
+            .map(|watch| watch.label())
+            .unwrap_or_default(),
+        app.link.label(),
+        app.idle
+            .as_ref()
+            .map(|idle| idle.label())
+            .unwrap_or_default(),
+        app.display.control.label(),
+        app.decoding_label(),
+        app.search
+            .as_ref()
+            .map(|search| search.label())
+            .unwrap_or_default(),
+        app.memory
+            .as_ref()
+            .map(|memory| memory.label(app.memory_used()))
+            .unwrap_or_default()
+    );
+    let mut error_warn_title = format!(
+        "{}{}{}",
+        app.error_warn_title,
+        app.error_warn.freeze_label(),
+        app.error_cursor_label()
+    );
+    let mut warnings_title = format!("{}{}", app.warnings_title, app.warnings.freeze_label());
+    let mut route_titles: Vec<String> = app
+        .routes
+        .iter()
+        .map(|route| format!("{}{}", route.title, route.pane.freeze_label()))
+        .collect();
+    if let Some(selection) = app.selection {
+        let (first, last) = selection.range();
+        let title = match selection.pane {
+            PaneId::Main => &mut main_title,
+            PaneId::ErrorWarn => &mut error_warn_title,
+            PaneId::Warnings => &mut warnings_title,
+            PaneId::Route(i) => &mut route_titles[i],
+        };
+        title.push_str(&format!(
+            " [VISUAL {} lines: y copy, f save, i inspect]",
+            last - first + 1
+        ));
+    }
+    if let Some(plot) = &app.plot {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(PLOT_HEIGHT), Constraint::Min(0)].as_ref())
+            .split(area);
+        draw_plot(f, plot, rows[0]);
+        area = rows[1];
+    }
+    if let Some(diagnostics) = app.reader.as_ref().and_then(|reader| reader.diagnostics()) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(DEBUG_HEIGHT), Constraint::Min(0)].as_ref())
+            .split(area);
+        draw_debug(f, &diagnostics.rows(app.backlog), rows[0]);
+        area = rows[1];
+    }
+
+    if let (true, Some(percent)) = (app.progress_gauge, app.progress) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(area);
+        let label = app.live_text().unwrap_or_default().to_string();
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+            .percent(percent)
+            .label(label);
+        f.render_widget(gauge, rows[0]);
+        area = rows[1];
+    }
+
+    let areas = if app.display.compact {
+        // One status line between the panes stands in for both titles
+        let (top, bottom) = if app.classify {
+            (
+                Constraint::Percentage(app.top_perc as u16),
+                Constraint::Min(0),
+            )
+        } else {
+            (Constraint::Min(0), Constraint::Length(0))
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([top, Constraint::Length(1), bottom].as_ref())
+            .split(area);
+        let mut spans = vec![Span::styled(
+            format!("▲ {}", main_title),
+            if app.success_flashing() {
+                success_style()
+            } else {
+                focus_style(app.focus == PaneId::Main)
+            },
+        )];
+        for (i, title) in route_titles.iter().enumerate() {
+            spans.push(Span::raw(" │ "));
+            spans.push(Span::styled(
+                format!("◆ {}", title),
+                focus_style(app.focus == PaneId::Route(i)),
+            ));
+        }
+        if app.classify {
+            spans.push(Span::raw(" │ "));
+            spans.push(Span::styled(
+                format!("▼ {}", error_warn_title),
+                focus_style(app.focus == PaneId::ErrorWarn),
+            ));
+        }
+        if app.split_warnings {
+            spans.push(Span::raw(" │ "));
+            spans.push(Span::styled(
+                format!("▼ {}", warnings_title),
+                focus_style(app.focus == PaneId::Warnings),
+            ));
+        }
+        let status = Line::from(spans);
+        f.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::REVERSED)),
+            chunks[1],
+        );
+        [chunks[0], chunks[2]]
+    } else if !app.classify {
+        [area, Rect::default()]
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Percentage(app.top_perc as u16),
+                    Constraint::Percentage(app.bot_perc as u16),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        [chunks[0], chunks[1]]
+    };
+
+    let partial = app.live.filter(|_| app.shows_partial());
+    let flashing = app.success_flashing();
+    let view = |id: PaneId, title: String| PaneView {
+        title,
+        focused: app.focus == id,
+        selection: app.selection.filter(|s| s.pane == id),
+        search: app
+            .search
+            .as_ref()
+            .filter(|search| search.scope.includes(id)),
+        responses: app.last_command.as_ref().filter(|_| app.display.responses),
+        hit: app
+            .search_hit
+            .filter(|&(pane, _)| pane == id)
+            .map(|(_, line)| line)
+            .or(app.error_cursor.filter(|_| id == PaneId::ErrorWarn)),
+        partial: partial.filter(|_| id == PaneId::Main),
+        flash: id == PaneId::Main && flashing,
+        sources: &app.sources,
+        display: app.display,
+    };
+    let main_view = view(PaneId::Main, main_title);
+    let error_warn_view = view(PaneId::ErrorWarn, error_warn_title);
+    let warnings_view = view(PaneId::Warnings, warnings_title);
+    let route_views: Vec<PaneView> = route_titles
+        .into_iter()
+        .enumerate()
+        .map(|(i, title)| view(PaneId::Route(i), title))
+        .collect();
+
+    // Routed panes share the main pane's space equally
+    let count = app.routes.len() as u32 + 1;
+    let direction = match app.route_layout {
+        RouteLayout::Rows => Direction::Vertical,
+        RouteLayout::Columns => Direction::Horizontal,
+    };
+    let top = Layout::default()
+        .direction(direction)
+        .constraints(vec![Constraint::Ratio(1, count); count as usize])
+        .split(areas[0]);
+
+    draw_pane(f, &mut app.main, main_view, top[0]);
+    for ((route, view), &rect) in app.routes.iter_mut().zip(route_views).zip(&top[1..]) {
+        draw_pane(f, &mut route.pane, view, rect);
+    }
+    // Combine error and warning data in the same pane, coloring each
+    // appropriately, unless warnings have a pane of their own beside it
+    if app.split_warnings {
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 2); 2].as_ref())
+            .split(areas[1]);
+        draw_pane(f, &mut app.error_warn, error_warn_view, bottom[0]);
+        draw_pane(f, &mut app.warnings, warnings_view, bottom[1]);
+    } else if app.classify {
+        draw_pane(f, &mut app.error_warn, error_warn_view, areas[1]);
+    }
+
+    if let Some(palette) = &app.palette {
+        draw_palette(f, palette, &app.keymap, area);
+    }
+    if let Some(menu) = &app.baud_menu {
+        draw_baud_menu(f, menu, &app.baud_presets, app.baud_rate, area);
+    }
+    if let Some(inspection) = &app.inspection {
+        draw_inspector(f, inspection, app.display.collapse_runs, area);
+    }
+    if let Some(commands) = &app.port_commands {
+        draw_port_commands(f, commands, area);
+    }
+}
+
+/// Centered note shown instead of the panes while the terminal is smaller
+/// than `min_size`.
+fn draw_too_small<B: Backend>(f: &mut Frame<B>, area: Rect, min_size: (u16, u16)) {
+    let lines = vec![
+        Line::from("Terminal too small"),
+        Line::from(format!(
+            "{}×{}, needs {}×{}",
+            area.width, area.height, min_size.0, min_size.1
+        )),
+    ];
+    let top = area.height.saturating_sub(lines.len() as u16) / 2;
+    let rect = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    f.render_widget(
+        Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        rect,
+    );
+}
+
+/// Command palette popup, towards the top of `area`.
+fn draw_palette<B: Backend>(f: &mut Frame<B>, palette: &Palette, keymap: &Keymap, area: Rect) {
+    let entries: Vec<(String, String)> = palette
+        .matches()
+        .iter()
+        .map(|command| {
+            (
+                command.name.to_string(),
+                keymap.key_label(command).to_string(),
+            )
+        })
+        .collect();
+    let title = format!(":{}", palette.query);
+    draw_menu(f, &title, &entries, palette.selected, 50, area);
+}
+
+/// Baud rate menu, with each rate's preset key and an entry for any other
+/// rate last.
+fn draw_baud_menu<B: Backend>(
+    f: &mut Frame<B>,
+    menu: &BaudMenu,
+    presets: &[(char, u32)],
+    current: u32,
+    area: Rect,
+) {
+    let mut entries: Vec<(String, String)> = menu
+        .rates
+        .iter()
+        .map(|&rate| {
+            let preset = presets
+                .iter()
+                .find(|&&(_, preset)| preset == rate)
+                .map(|(key, _)| format!("Alt+{}", key))
+                .unwrap_or_default();
+            (rate.to_string(), preset)
+        })
+        .collect();
+    entries.push(("Custom...".to_string(), String::new()));
+    let title = format!("Baud rate, now {}", current);
+    draw_menu(f, &title, &entries, menu.selected, 30, area);
+}
+
+/// List popup towards the top of `area`, with a name and a dimmed key on
+/// each row and the `selected` one highlighted.
+fn draw_menu<B: Backend>(
+    f: &mut Frame<B>,
+    title: &str,
+    entries: &[(String, String)],
+    selected: usize,
+    width: u16,
+    area: Rect,
+) {
+    let width = width.min(area.width);
+    let key_width = entries
+        .iter()
+        .map(|(_, key)| key.chars().count())
+        .chain([4])
+        .max()
+        .unwrap_or(4);
+    let name_width = (width as usize).saturating_sub(key_width + 4);
+    let lines: Vec<Line> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, (name, key))| {
+            let mut style = Style::default();
+            if i == selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Line::from(vec![
+                Span::styled(format!(" {:<width$}", name, width = name_width), style),
+                Span::styled(
+                    format!("{:>width$} ", key, width = key_width),
+                    style.fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height / 8,
+        width,
+        height: height.min(area.height - area.height / 8),
+    };
+    // Keep the selection in view when the list is taller than the popup
+    let visible = popup.height.saturating_sub(2) as usize;
+    let scroll = (selected + 1).saturating_sub(visible);
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines)
+            .scroll((scroll as u16, 0))
+            .block(Block::default().title(title).borders(Borders::ALL)),
+        popup,
+    );
+}
+
+/// Popup with the exact bytes of one line, centred over `area`.
+fn draw_inspector<B: Backend>(
+    f: &mut Frame<B>,
+    inspection: &Inspection,
+    collapse: usize,
+    area: Rect,
+) {
+    // Lines that never came from the device, such as those sent, have only
+    // their text to show
+    let bytes = inspection
+        .raw
+        .as_deref()
+        .unwrap_or(inspection.text.as_bytes());
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Text: ", Style::default().fg(Color::DarkGray)),
+            // Pictures keep control characters visible without moving the cursor
+            Span::raw(
+                text::show_control_chars(
+                    &text::collapse_runs(&inspection.text, collapse),
+                    text::ControlDisplay::Pictures,
+                )
+                .into_owned(),
+            ),
+        ]),
+        Line::from(""),
+    ];
+    lines.extend(text::hex_dump(bytes, collapse).into_iter().map(Line::from));
+
+    // Hex dump rows are 78 columns wide; two more go to the borders
+    let width = 80.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let title = format!(
+        "Line {}: {} bytes {} (Esc to close)",
+        inspection.number,
+        bytes.len(),
+        if inspection.raw.is_some() {
+            "received"
+        } else {
+            "as shown"
+        }
+    );
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(focus_style(true)),
+        ),
+        popup,
+    );
+}
+
+/// Popup with the commands opening the port in other terminals.
+fn draw_port_commands<B: Backend>(f: &mut Frame<B>, commands: &[String], area: Rect) {
+    let lines: Vec<Line> = commands
+        .iter()
+        .map(|command| Line::from(command.as_str()))
+        .collect();
+    let title = "Same port in other terminals, copied (Esc to close)";
+    let longest = commands
+        .iter()
+        .map(|command| command.chars().count())
+        .chain([title.len()])
+        .max()
+        .unwrap_or(0);
+    let width = (longest as u16 + 2).min(area.width);
+    // Long commands wrap onto more rows in a narrow terminal
+    let rows: usize = commands
+        .iter()
+        .map(|command| {
+            command
+                .chars()
+                .count()
+                .div_ceil(width.saturating_sub(2).max(1) as usize)
+        })
+        .sum();
+    let height = (rows as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(focus_style(true)),
+        ),
+        popup,
+    );
+}
+
+/// Pane with what the reader has been doing, for `--debug`.
+fn draw_debug<B: Backend>(f: &mut Frame<B>, rows: &[String], area: Rect) {
+    let lines: Vec<Line> = rows.iter().map(|row| Line::from(row.as_str())).collect();
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title("Reader diagnostics")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        ),
+        area,
+    );
+}
+
+/// Line chart of the values extracted for the plot pane.
+fn draw_plot<B: Backend>(f: &mut Frame<B>, plot: &Plot, area: Rect) {
+    let points: Vec<(f64, f64)> = plot
+        .values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| (i as f64, value))
+        .collect();
+    let (min, max) = plot.range().unwrap_or((0.0, 1.0));
+    // Keep a flat line off the top and bottom borders
+    let (low, high) = if min == max {
+        (min - 1.0, max + 1.0)
+    } else {
+        (min, max)
+    };
+    let title = match plot.values.back() {
+        Some(last) => format!("Plot: {} (min {}, max {})", last, min, max),
+        None => "Plot: waiting for values".to_string(),
+    };
+    let dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Cyan))
+        .data(&points);
+    let chart = Chart::new(vec![dataset])
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .x_axis(Axis::default().bounds([0.0, (plot.window() - 1) as f64]))
+        .y_axis(
+            Axis::default()
+                .bounds([low, high])
+                .labels(vec![
+                    Span::raw(format!("{}", low)),
+                    Span::raw(format!("{}", high)),
+                ])
+                .style(Style::default().fg(Color::DarkGray)),
+        );
+    f.render_widget(chart, area);
+}
+
+/// Colour of the heat column for a line with `density` lines around it.
+fn heat_color(density: usize) -> Color {
+    HEAT_COLORS
+        .iter()
+        .rev()
+        .find(|&&(least, _)| density >= least)
+        .map_or(Color::DarkGray, |&(_, color)| color)
+}
+
+/// Per-frame settings for drawing one pane.
+struct PaneView<'a> {
+    title: String,
+    focused: bool,                        // Navigation keys act on this pane
+    selection: Option<Selection>,         // Visual mode range in this pane
+    search: Option<&'a Search>,           // Matching lines are highlighted
+    responses: Option<&'a ResponseMatch>, // Lines answering the last command are marked
+    hit: Option<usize>,                   // Current search match or error cursor in this pane
+    partial: Option<usize>,               // Line still being received, shown apart
+    flash: bool,                          // Border green for a success line
+    sources: &'a [Source],                // Tags of the ports read, when there are several
+    display: Display,
+}
+
+fn draw_pane<B: Backend>(f: &mut Frame<B>, pane: &mut Pane, view: PaneView, area: Rect) {
+    let PaneView {
+        title,
+        focused,
+        selection,
+        search,
+        responses,
+        hit,
+        partial,
+        flash,
+        sources,
+        display,
+    } = view;
+
+    // Two rows go to the borders, which compact mode leaves out
+    let block = if display.compact {
+        Block::default()
+    } else {
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(if flash {
+                success_style()
+            } else {
+                focus_style(focused)
+            })
+    };
+    let inner = block.inner(area);
+    pane.auto_scroll(inner.height as usize);
+
+    let trimmed = pane.trimmed;
+    let number_width = (trimmed + pane.lines.len()).to_string().len();
+    let mut gutter_width = 0;
+    if display.heat {
+        gutter_width += 2;
+    }
+    if display.line_numbers {
+        gutter_width += number_width + 1;
+    }
+    if display.timestamps != Timestamps::Off {
+        gutter_width += TIMESTAMP_WIDTH + 1;
+    }
+    if display.deltas {
+        gutter_width += DELTA_WIDTH + 1;
+    }
+    if display.byte_counts {
+        gutter_width += BYTE_COUNT_WIDTH + 1;
+    }
+    // Lines without a port, such as those sent, leave the tag column blank
+    let tag_width = sources
+        .iter()
+        .map(|source| source.label().chars().count())
+        .max()
+        .unwrap_or(0);
+    if tag_width > 0 {
+        gutter_width += tag_width + 1;
+    }
+    // Room for the text of each row when wrapping, less the wrap glyph
+    let glyph_width = usize::from(display.wrap_glyph.is_some());
+    let wrap_width = (inner.width as usize)
+        .saturating_sub(gutter_width + glyph_width)
+        .max(1);
+
+    let mut rows = Vec::with_capacity(pane.lines.len());
+    let mut scroll_rows = None;
+    for (i, line) in pane.lines.iter().enumerate().skip(pane.first_shown()) {
+        if i == pane.scroll_offset {
+            scroll_rows = Some(rows.len());
+        }
+        let color = highlight::color_for(&pane.highlights, &line.text).unwrap_or(line.color);
+        let mut style = Style::default().fg(color);
+        if search.is_some_and(|search| search.is_match(&line.text)) {
+            style = style.bg(Color::DarkGray);
+        }
+        if responses.is_some_and(|responses| responses.is_match(line)) {
+            style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+        if partial == Some(trimmed + i) {
+            style = style.add_modifier(Modifier::DIM | Modifier::ITALIC);
+        }
+        if let Some(selection) = selection {
+            let (first, last) = selection.range();
+            if (first..=last).contains(&(trimmed + i)) {
+                let cursor = selection.cursor == trimmed + i;
+                style = style.patch(display.selection.style(cursor));
+            }
+        }
+        if hit == Some(trimmed + i) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        let mut gutter = Vec::with_capacity(6);
+        if display.heat {
+            gutter.push(Span::styled(
+                "\u{258c} ",
+                Style::default().fg(heat_color(pane.density(i, HEAT_WINDOW))),
+            ));
+        }
+        if display.line_numbers {
+            gutter.push(Span::styled(
+                format!("{:>width$} ", trimmed + i + 1, width = number_width),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if display.timestamps != Timestamps::Off {
+            let starts_burst = i.checked_sub(1).is_none_or(|prev| {
+                line.time
+                    .duration_since(pane.lines[prev].time)
+                    .is_ok_and(|gap| gap > display.burst_gap)
+            });
+            let stamp = if display.timestamps == Timestamps::Every || starts_burst {
+                WallTime::of(line.time).hms_millis()
+            } else {
+                String::new()
+            };
+            gutter.push(Span::styled(
+                format!("{:>width$} ", stamp, width = TIMESTAMP_WIDTH),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if display.deltas {
+            // Only gaps above the threshold are worth the clutter
+            let gap = i
+                .checked_sub(1)
+                .and_then(|prev| line.time.duration_since(pane.lines[prev].time).ok())
+                .filter(|&gap| gap > display.delta_threshold);
+            let delta = gap
+                .map(|gap| format!("+{:.3}s", gap.as_secs_f64()))
+                .unwrap_or_default();
+            gutter.push(Span::styled(
+                format!("{:>width$} ", delta, width = DELTA_WIDTH),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if display.byte_counts {
+            gutter.push(Span::styled(
+                format!(
+                    "{:>width$} ",
+                    format!("{}B", line.text.len()),
+                    width = BYTE_COUNT_WIDTH
+                ),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if tag_width > 0 {
+            let source = line.source.and_then(|source| sources.get(source));
+            gutter.push(Span::styled(
+                format!(
+                    "{:<width$} ",
+                    source.map(Source::label).unwrap_or_default(),
+                    width = tag_width
+                ),
+                source.map_or(Style::default(), |source| Style::default().fg(source.color)),
+            ));
+        }
+        let shown = match text::show_nul(&line.text, display.nul) {
+            Cow::Borrowed(text) => shown_text(text, display),
+            Cow::Owned(text) => Cow::Owned(shown_text(&text, display).into_owned()),
+        };
+        if !display.wrap {
+            gutter.push(Span::styled(shown, style));
+            rows.push(Line::from(gutter));
+            continue;
+        }
+
+        let chars: Vec<char> = shown.chars().collect();
+        let mut pieces = chars.chunks(wrap_width).peekable();
+        let mut first = true;
+        while let Some(piece) = pieces.next() {
+            let mut spans = if first {
+                std::mem::take(&mut gutter)
+            } else {
+                // Continuation rows keep the gutter column clear
+                vec![Span::raw(" ".repeat(gutter_width))]
+            };
+            first = false;
+            spans.push(Span::styled(piece.iter().collect::<String>(), style));
+            if let (Some(glyph), Some(_)) = (display.wrap_glyph, pieces.peek()) {
+                spans.push(Span::styled(
+                    glyph.to_string(),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            rows.push(Line::from(spans));
+        }
+        if first {
+            // Empty line
+            rows.push(Line::from(gutter));
+        }
+    }
+    // A following pane keeps its last row at the bottom, however lines wrap
+    let scroll_rows = if pane.is_following() {
+        rows.len().saturating_sub(inner.height as usize)
+    } else {
+        scroll_rows.unwrap_or(rows.len())
+    };
+
+    let text = Paragraph::new(rows)
+        .block(block)
+        .scroll((scroll_rows as u16, 0));
+
+    f.render_widget(text, area);
+}
+
+/// `text` with long runs collapsed and control characters rendered as
+/// `display` says.
+fn shown_text(text: &str, display: Display) -> Cow<'_, str> {
+    match text::collapse_runs(text, display.collapse_runs) {
+        Cow::Borrowed(_) => text::show_control_chars(text, display.control),
+        Cow::Owned(collapsed) => {
+            Cow::Owned(text::show_control_chars(&collapsed, display.control).into_owned())
+        }
+    }
+}
+
+/// Highlight for the focused pane's border or title.
+fn focus_style(focused: bool) -> Style {
+    if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    }
+}
+
+/// Style of the main pane's border just after a success line.
+fn success_style() -> Style {
+    Style::default()
+        .fg(Color::Green)
+        .add_modifier(Modifier::BOLD)
+}